@@ -0,0 +1,275 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{spanned::Spanned, Attribute, DeriveInput, Data, Fields, Ident, Lit, LitStr, Type};
+use quote::quote;
+
+/// UI metadata from a field's `#[form(...)]` attribute.
+#[derive(Default)]
+struct FormAttrs {
+    label: Option<LitStr>,
+    placeholder: Option<LitStr>,
+    help: Option<LitStr>,
+    // Name of a `Widget` variant.
+    widget: Option<String>,
+    // Show the field only when another field, by its serialized name, has this value.
+    show_if: Option<(LitStr, Lit)>,
+    // Title of the section the field belongs to.
+    section: Option<LitStr>,
+}
+
+impl FormAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Option<Self>> {
+        let mut out: Option<FormAttrs> = None;
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("form")) {
+            let form = out.get_or_insert_with(FormAttrs::default);
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("show_if") {
+                    return meta.parse_nested_meta(|condition| {
+                        let field = condition.path.get_ident().ok_or_else(|| condition.error("expected a field name"))?;
+                        let value: Lit = condition.value()?.parse()?;
+                        form.show_if = Some((LitStr::new(&field.to_string(), field.span()), value));
+                        Ok(())
+                    });
+                }
+                let value: LitStr = meta.value()?.parse()?;
+                if meta.path.is_ident("label") {
+                    form.label = Some(value);
+                } else if meta.path.is_ident("placeholder") {
+                    form.placeholder = Some(value);
+                } else if meta.path.is_ident("help") {
+                    form.help = Some(value);
+                } else if meta.path.is_ident("widget") {
+                    form.widget = Some(widget_variant(&value)?.to_string());
+                } else if meta.path.is_ident("section") {
+                    form.section = Some(value);
+                } else {
+                    return Err(meta.error("unknown form attribute, expected one of label, placeholder, help, widget, show_if, section"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// Sections from a struct's `#[form(section(title = "...", ...))]` attributes, as `Section` expressions.
+fn parse_sections(attrs: &[Attribute]) -> syn::Result<Vec<TokenStream>> {
+    let mut sections = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("form")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("section") {
+                return Err(meta.error("unknown form attribute, expected section"));
+            }
+            let mut title: Option<LitStr> = None;
+            let mut options = vec![];
+            meta.parse_nested_meta(|option| {
+                if option.path.is_ident("title") {
+                    title = Some(option.value()?.parse()?);
+                } else if option.path.is_ident("description") {
+                    let description: LitStr = option.value()?.parse()?;
+                    options.push(quote! { .description(#description) });
+                } else if option.path.is_ident("collapsible") {
+                    options.push(quote! { .collapsible() });
+                } else if option.path.is_ident("collapsed") {
+                    options.push(quote! { .collapsed() });
+                } else {
+                    return Err(option.error("unknown section option, expected one of title, description, collapsible, collapsed"));
+                }
+                Ok(())
+            })?;
+            let title = title.ok_or_else(|| meta.error("section needs a title"))?;
+            sections.push(quote! { Section::new(#title) #(#options)* });
+            Ok(())
+        })?;
+    }
+    Ok(sections)
+}
+
+/// Maps the widget names accepted in `#[form(widget = "...")]` to `Widget` variants.
+fn widget_variant(name: &LitStr) -> syn::Result<&'static str> {
+    let variant = match name.value().to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+        "text" => "Text",
+        "textarea" => "TextArea",
+        "password" => "Password",
+        "number" => "Number",
+        "checkbox" => "Checkbox",
+        "select" => "Select",
+        "multiselect" => "MultiSelect",
+        "table" => "Table",
+        "radio" => "Radio",
+        "date" => "Date",
+        "datetime" => "DateTime",
+        "hidden" => "Hidden",
+        "slider" => "Slider",
+        "rating" => "Rating",
+        _ => return Err(syn::Error::new(name.span(), "unknown widget, expected one of text, textarea, password, number, checkbox, select, multiselect, table, radio, date, datetime, hidden, slider, rating")),
+    };
+    Ok(variant)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn has_serde_default(attrs: &[Attribute]) -> bool {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+        .any(|attr| attr.meta.require_list().is_ok_and(|list| list.tokens.to_string().contains("default")))
+}
+
+fn option_lit(lit: &Option<LitStr>) -> TokenStream {
+    match lit {
+        Some(lit) => quote! { Some(#lit) },
+        None => quote! { None },
+    }
+}
+
+/// Implements `JsonSchema` for a struct, adding the UI metadata of its `#[form(...)]` attributes to the schema.
+/// The schema itself comes from a hidden copy of the struct deriving `JsonSchema`, so serde and schemars attributes work as usual.
+/// Each field with a `#[form(...)]` attribute gets its schema from a generated function that adds the metadata.
+pub fn middle_form_inner(input: TokenStream) -> TokenStream {
+    let input = match syn::parse2::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(err) => return err.to_compile_error(),
+    };
+    // The schema functions of the fields are free functions, which can't name the struct's type parameters.
+    if !input.generics.params.is_empty() {
+        return syn::Error::new(input.generics.span(), "MiddleForm doesn't support generic structs").to_compile_error();
+    }
+    let mut shadow = input.clone();
+    let fields = match &mut shadow.data {
+        Data::Struct(data) => match &mut data.fields {
+            Fields::Named(fields) => &mut fields.named,
+            _ => return syn::Error::new(input.ident.span(), "MiddleForm needs a struct with named fields").to_compile_error(),
+        },
+        _ => return syn::Error::new(input.ident.span(), "MiddleForm needs a struct with named fields").to_compile_error(),
+    };
+    let sections = match parse_sections(&input.attrs) {
+        Ok(sections) => sections,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    let mut field_fns = vec![];
+    for field in fields.iter_mut() {
+        let form = match FormAttrs::parse(&field.attrs) {
+            Ok(Some(form)) => form,
+            Ok(None) => continue,
+            Err(err) => return err.to_compile_error(),
+        };
+        field.attrs.retain(|attr| !attr.path().is_ident("form"));
+
+        let field_name = field.ident.as_ref().expect("named field");
+        let fn_name = Ident::new(&format!("__middle_form_{field_name}"), Span::call_site());
+        let fn_name_str = fn_name.to_string();
+        let ty = &field.ty;
+        let (label, placeholder, help) = (option_lit(&form.label), option_lit(&form.placeholder), option_lit(&form.help));
+        let section = option_lit(&form.section);
+        let widget = option_lit(&form.widget.map(|widget| LitStr::new(&widget, Span::call_site())));
+        let show_if = match &form.show_if {
+            Some((field, value)) => quote! { Some((#field, serde_json::json!(#value))) },
+            None => quote! { None },
+        };
+        field_fns.push(quote! {
+            fn #fn_name(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+                let schema = generator.subschema_for::<#ty>();
+                form_field_schema(schema, #label, #placeholder, #help, #widget, #show_if, #section)
+            }
+        });
+        field.attrs.push(syn::parse_quote! { #[schemars(schema_with = #fn_name_str)] });
+        // A schema function hides that the field is optional, so mark it as defaulted to keep it out of `required`.
+        if is_option(ty) && !has_serde_default(&field.attrs) {
+            field.attrs.push(syn::parse_quote! { #[serde(default, skip_serializing_if = "Option::is_none")] });
+        }
+    }
+
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let shadow_name = Ident::new(&format!("__MiddleForm{name}"), Span::call_site());
+    shadow.ident = shadow_name.clone();
+    shadow.attrs.retain(|attr| !attr.path().is_ident("derive") && !attr.path().is_ident("form"));
+    let schema = if sections.is_empty() {
+        quote! { <#shadow_name as JsonSchema>::json_schema(generator) }
+    } else {
+        quote! { form_sections(<#shadow_name as JsonSchema>::json_schema(generator), vec![#(#sections),*]) }
+    };
+
+    quote! {
+        const _: () = {
+            #(#field_fns)*
+
+            #[derive(JsonSchema)]
+            #[allow(dead_code)]
+            #shadow
+
+            impl JsonSchema for #name {
+                fn schema_name() -> ::std::string::String {
+                    ::std::string::String::from(#name_str)
+                }
+                fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+                    #schema
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use crate::form::*;
+
+    #[test]
+    fn test_middle_form() {
+        let generated = middle_form_inner(quote!(
+            #[derive(Deserialize, MiddleForm)]
+            #[form(section(title = "Contact", collapsed))]
+            struct Contact {
+                #[form(label = "Email address", placeholder = "you@example.com", section = "Contact")]
+                email: String,
+                #[form(widget = "textarea", show_if(contact_me = true))]
+                notes: Option<String>,
+                age: u32,
+            }
+        ));
+        let compare = quote!(
+            const _: () = {
+                fn __middle_form_email(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+                    let schema = generator.subschema_for::<String>();
+                    form_field_schema(schema, Some("Email address"), Some("you@example.com"), None, None, None, Some("Contact"))
+                }
+                fn __middle_form_notes(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+                    let schema = generator.subschema_for::<Option<String> >();
+                    form_field_schema(schema, None, None, None, Some("TextArea"), Some(("contact_me", serde_json::json!(true))), None)
+                }
+
+                #[derive(JsonSchema)]
+                #[allow(dead_code)]
+                struct __MiddleFormContact {
+                    #[schemars(schema_with = "__middle_form_email")]
+                    email: String,
+                    #[schemars(schema_with = "__middle_form_notes")]
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    notes: Option<String>,
+                    age: u32,
+                }
+
+                impl JsonSchema for Contact {
+                    fn schema_name() -> ::std::string::String {
+                        ::std::string::String::from("Contact")
+                    }
+                    fn json_schema(generator: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+                        form_sections(<__MiddleFormContact as JsonSchema>::json_schema(generator), vec![Section::new("Contact").collapsed()])
+                    }
+                }
+            };
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+
+        let generated = middle_form_inner(quote!(struct A { #[form(widget = "dial")] a: u32 }));
+        assert!(generated.to_string().contains("unknown widget"));
+        let generated = middle_form_inner(quote!(#[form(section(collapsed))] struct A { a: u32 }));
+        assert!(generated.to_string().contains("section needs a title"));
+    }
+}
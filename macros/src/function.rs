@@ -67,6 +67,7 @@ pub fn middle_fn_inner(input: proc_macro2::TokenStream) -> proc_macro2::TokenStr
 
     // We have to reassign/clone the original fn ident for Rust to like our macro.
     let fn_name = input.sig.ident.clone();
+    let fn_name_str = fn_name.to_string();
 
     // We'll need to wrap function inputs and outputs in a special struct.
     let user_fn_in_struct_ident = Ident::new(&format!("UserFnIn__{}", input.sig.ident), Span::call_site());
@@ -90,6 +91,8 @@ pub fn middle_fn_inner(input: proc_macro2::TokenStream) -> proc_macro2::TokenStr
 
         #[no_mangle]
         pub fn #user_fn_name(offset: u32, size: u32) -> u32 {
+            // Remember which function is running, e.g. for the default User-Agent.
+            set_current_fn(#fn_name_str);
             // The host calls us with a JSON value.
             // There seems to be no other good way of constructing a value on the host side.
             let input_json: serde_json::Value = value_from_host(offset, size);
@@ -100,6 +103,8 @@ pub fn middle_fn_inner(input: proc_macro2::TokenStream) -> proc_macro2::TokenStr
                 // Map each input argument identity into (for example) `input.a, input.b, input.c`
                 #( input . #input_args_idents ),*
             );
+            // If the function failed with `RetryLater`, ask the host to run it again.
+            request_retry((&RetryProbe(&output)).retry_later());
             // Put the user's output in our output struct, which has the serialize derive macro implemented
             let output = #user_fn_out_struct_ident (output);
             // Convert the return value into JSON, so the host can parse it.
@@ -169,10 +174,12 @@ mod test {
             
             #[no_mangle]
             pub fn user_fn__test(offset: u32, size: u32) -> u32 {
+                set_current_fn("test");
                 let input_json: serde_json::Value = value_from_host(offset, size);
                 let input: UserFnIn__test = serde_json::from_value(input_json)
                     .expect("user function input could not be serialzied into JSON");
                 let output = test(input.a, input.b, input.c);
+                request_retry((&RetryProbe(&output)).retry_later());
                 let output = UserFnOut__test(output);
                 let output_json = serde_json::value::to_value(output)
                     .expect("user function output could not be serialized into JSON");
@@ -0,0 +1,92 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{punctuated::Punctuated, Expr, Ident, Token, parse::Parser};
+use quote::quote;
+
+fn parse_steps(input: TokenStream) -> syn::Result<Vec<Expr>> {
+    let steps = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(input)?;
+    if steps.is_empty() {
+        return Err(syn::Error::new(Span::call_site(), "expected at least one step"));
+    }
+    Ok(steps.into_iter().collect())
+}
+
+/// Runs each step in its own branch of a group, so their host calls are kept apart, and resumes once all are done.
+/// Evaluates to a `Resumable` of a tuple of the outputs.
+pub fn join_inner(input: TokenStream) -> TokenStream {
+    let steps = match parse_steps(input) {
+        Ok(steps) => steps,
+        Err(err) => return err.to_compile_error(),
+    };
+    // Mixed-site spans keep these locals out of reach of the steps, so they can't capture or shadow them.
+    let group = Ident::new("__group", Span::mixed_site());
+    let results: Vec<Ident> = (0..steps.len()).map(|i| Ident::new(&format!("__result{i}"), Span::mixed_site())).collect();
+    let indices = 0..steps.len();
+    quote! {
+        {
+            let #group = parallel_begin();
+            #( let #results = branch(#group, #indices, || #steps); )*
+            if #( matches!(#results, Resumable::Cancelled) )||* {
+                Resumable::Cancelled
+            } else {
+                match (#(#results,)*) {
+                    (#(Resumable::Ready(#results),)*) => Resumable::Ready((#(#results,)*)),
+                    _ => Resumable::Pause,
+                }
+            }
+        }
+    }
+}
+
+/// Runs each step in its own branch of a group, and resumes once the first is done.
+/// Evaluates to a `Resumable` of the index and output of that step.
+pub fn select_inner(input: TokenStream) -> TokenStream {
+    let steps = match parse_steps(input) {
+        Ok(steps) => steps,
+        Err(err) => return err.to_compile_error(),
+    };
+    let group = Ident::new("__group", Span::mixed_site());
+    let indices = 0..steps.len();
+    quote! {
+        {
+            let #group = parallel_begin();
+            select_results(#group, vec![#( branch(#group, #indices, || #steps) ),*])
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use crate::join::*;
+
+    #[test]
+    fn test_join() {
+        let generated = join_inner(quote!(prompt_user::<A, _>("ann"), pause_until(noon),));
+        let compare = quote!(
+            {
+                let __group = parallel_begin();
+                let __result0 = branch(__group, 0usize, || prompt_user::<A, _>("ann"));
+                let __result1 = branch(__group, 1usize, || pause_until(noon));
+                if matches!(__result0, Resumable::Cancelled) || matches!(__result1, Resumable::Cancelled) {
+                    Resumable::Cancelled
+                } else {
+                    match (__result0, __result1,) {
+                        (Resumable::Ready(__result0), Resumable::Ready(__result1),) => Resumable::Ready((__result0, __result1,)),
+                        _ => Resumable::Pause,
+                    }
+                }
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+
+        let generated = select_inner(quote!(await_event("paid"), await_event("refunded")));
+        let compare = quote!(
+            {
+                let __group = parallel_begin();
+                select_results(__group, vec![branch(__group, 0usize, || await_event("paid")), branch(__group, 1usize, || await_event("refunded"))])
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+        assert!(join_inner(quote!()).to_string().contains("expected at least one step"));
+    }
+}
@@ -5,6 +5,11 @@ extern crate proc_macro2;
 
 mod multistep_function;
 mod function;
+mod request;
+mod form;
+mod join;
+mod version;
+mod query;
 
 /// Copies the "doc" attribute of a function.
 /// This is the triple-/ comment block that actually becomes a #[doc=""] attribute.
@@ -44,3 +49,56 @@ pub fn middle_multistep_fn(_attr: proc_macro::TokenStream, input: proc_macro::To
     let output = multistep_function::middle_multistep_function_inner(input.into());
     proc_macro::TokenStream::from(output)
 }
+
+/// Exports a function the host can call on a paused multistep run without resuming it, e.g. to show which step it's at.
+/// Queries take no arguments and usually read `workflow::state`.
+#[proc_macro_attribute]
+pub fn middle_query(_attr: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = query::middle_query_inner(input.into());
+    proc_macro::TokenStream::from(output)
+}
+
+/// Marks which version of a multistep function this is, e.g. `#[workflow_version(2)]`, next to `#[middle_multistep_fn]`.
+/// Bump it when the steps change; `workflow::version_of_run()` then tells runs started under older code apart.
+#[proc_macro_attribute]
+pub fn workflow_version(attr: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = version::workflow_version_inner(attr.into(), input.into());
+    proc_macro::TokenStream::from(output)
+}
+
+/// Makes a request in one line, e.g. `request!(POST "https://api.x.com/v1/items", bearer = token, json = { "name": name })?`.
+/// Accepts `bearer`, `basic = (username, password)`, `header = (name, value)`, `json`, `body`, `timeout` and `retry`,
+/// each of which may be given more than once. Evaluates to the result of `RequestBuilder::call`.
+#[proc_macro]
+pub fn request(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = request::request_inner(input.into());
+    proc_macro::TokenStream::from(output)
+}
+
+/// Implements `JsonSchema` for a struct, adding UI metadata from `#[form(...)]` field attributes,
+/// e.g. `#[form(label = "Email address", placeholder = "you@example.com", widget = "textarea")]`.
+/// Use it instead of `#[derive(JsonSchema)]`; `prompt::<T>()` then shows the labels, placeholders and widgets.
+/// Accepts `label`, `placeholder`, `help`, `widget`, `section = "title"`, and `show_if(other_field = value)`
+/// to show a field only while another field has the given value.
+/// Sections can be described on the struct with `#[form(section(title = "...", description = "...", collapsed))]`.
+#[proc_macro_derive(MiddleForm, attributes(form))]
+pub fn middle_form(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = form::middle_form_inner(input.into());
+    proc_macro::TokenStream::from(output)
+}
+
+/// Runs several steps that may pause side by side, e.g. `join!(prompt_user::<A, _>("ann"), prompt_user::<B, _>("bob"))?`,
+/// and resumes once all are done. Evaluates to a `Resumable` of a tuple of their outputs.
+#[proc_macro]
+pub fn join(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = join::join_inner(input.into());
+    proc_macro::TokenStream::from(output)
+}
+
+/// Runs several steps of the same type that may pause side by side, e.g. `select!(await_event("paid"), await_event("refunded"))?`,
+/// and resumes once the first is done. Evaluates to a `Resumable` of that step's index and output.
+#[proc_macro]
+pub fn select(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let output = join::select_inner(input.into());
+    proc_macro::TokenStream::from(output)
+}
@@ -95,6 +95,7 @@ pub fn middle_multistep_function_inner(input: proc_macro2::TokenStream) -> proc_
 
     // We have to reassign/clone the original fn ident for Rust to like our macro.
     let fn_name = input.sig.ident.clone();
+    let fn_name_str = fn_name.to_string();
 
     // We'll need to wrap function inputs and outputs in a special struct.
     let user_fn_in_struct_ident = Ident::new(&format!("UserMultistepFnIn__{}", input.sig.ident), Span::call_site());
@@ -118,6 +119,8 @@ pub fn middle_multistep_function_inner(input: proc_macro2::TokenStream) -> proc_
 
         #[no_mangle]
         pub fn #user_fn_name(offset: u32, size: u32) -> u32 {
+            // Remember which function is running, e.g. for the default User-Agent.
+            set_current_fn(#fn_name_str);
             // The host calls us with a JSON value.
             // There seems to be no other good way of constructing a value on the host side.
             let input_json: serde_json::Value = value_from_host(offset, size);
@@ -128,6 +131,10 @@ pub fn middle_multistep_function_inner(input: proc_macro2::TokenStream) -> proc_
                 // Map each input argument identity into (for example) `input.a, input.b, input.c`
                 #( input . #input_args_idents ),*
             );
+            // If the host is cancelling the run, give the function's cancellation hooks a chance to clean up.
+            let output = finish_multistep(output);
+            // If the function failed with `RetryLater`, ask the host to run it again.
+            request_retry((&RetryProbe(&output)).retry_later());
             // Convert the return value into JSON, so the host can parse it.
             let output_json = serde_json::value::to_value(output).expect("user multi-step function output could not be serialized into JSON");
             // Make the result available to the host.
@@ -196,10 +203,13 @@ mod test {
             
             #[no_mangle]
             pub fn user_multistep_fn__test(offset: u32, size: u32) -> u32 {
+                set_current_fn("test");
                 let input_json: serde_json::Value = value_from_host(offset, size);
                 let input: UserMultistepFnIn__test = serde_json::from_value(input_json)
                     .expect("user multi-step function input could not be serialzied into JSON");
                 let output = test(input.a, input.b, input.c);
+                let output = finish_multistep(output);
+                request_retry((&RetryProbe(&output)).retry_later());
                 let output_json = serde_json::value::to_value(output)
                     .expect("user multi-step function output could not be serialized into JSON");
                 // Hmm. You know, we could try and stuff these two u32s into a i64. 
@@ -0,0 +1,56 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use syn::{spanned::Spanned, ItemFn};
+use quote::quote;
+
+/// Exports a function the host can call on a paused run, e.g. to show which step it's at, without resuming it.
+/// Queries take no arguments, and usually read `workflow::state`.
+pub fn middle_query_inner(input: TokenStream) -> TokenStream {
+    let input = match syn::parse2::<ItemFn>(input) {
+        Ok(input) => input,
+        Err(err) => return err.to_compile_error(),
+    };
+    if !input.sig.inputs.is_empty() {
+        return syn::Error::new(input.sig.inputs.span(), "queries can't take arguments").to_compile_error();
+    }
+    let fn_name = &input.sig.ident;
+    let query_fn_name = Ident::new(&format!("user_query__{fn_name}"), Span::call_site());
+    quote! {
+        #input
+
+        #[no_mangle]
+        pub fn #query_fn_name() -> u32 {
+            let output_json = serde_json::value::to_value(#fn_name()).expect("query output could not be serialized into JSON");
+            let (offset, size) = value_to_host(&output_json);
+            vec_parts_to_host(offset, size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use crate::query::*;
+
+    #[test]
+    fn test_middle_query() {
+        let generated = middle_query_inner(quote!(
+            fn order_status() -> Option<String> {
+                state("status").get()
+            }
+        ));
+        let compare = quote!(
+            fn order_status() -> Option<String> {
+                state("status").get()
+            }
+
+            #[no_mangle]
+            pub fn user_query__order_status() -> u32 {
+                let output_json = serde_json::value::to_value(order_status()).expect("query output could not be serialized into JSON");
+                let (offset, size) = value_to_host(&output_json);
+                vec_parts_to_host(offset, size)
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+        assert!(middle_query_inner(quote!(fn a(b: u32) -> u32 { b })).to_string().contains("can't take arguments"));
+    }
+}
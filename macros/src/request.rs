@@ -0,0 +1,125 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::{Ident, Expr, Token, parse::{Parse, ParseStream}};
+use quote::quote;
+
+/// A `key = value` argument of the `request!` macro.
+struct Arg {
+    key: Ident,
+    // The raw tokens of the value, so JSON literals such as `{ "name": name }` can be passed along to `json!`.
+    value: TokenStream,
+}
+
+/// Everything inside `request!(...)`.
+struct RequestInput {
+    method: Ident,
+    url: Expr,
+    args: Vec<Arg>,
+}
+
+impl Parse for RequestInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let method: Ident = input.parse()?;
+        let url: Expr = input.parse()?;
+        let mut args = vec![];
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            // Allow a trailing comma.
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let mut value = TokenStream::new();
+            while !input.is_empty() && !input.peek(Token![,]) {
+                value.extend([input.parse::<TokenTree>()?]);
+            }
+            if value.is_empty() {
+                return Err(syn::Error::new(key.span(), format!("`{key}` needs a value")));
+            }
+            args.push(Arg { key, value });
+        }
+        Ok(RequestInput { method, url, args })
+    }
+}
+
+/// Expands `request!(POST "https://...", bearer = token, json = { ... })` into a chain of `RequestBuilder` calls,
+/// ending in `call()`. Like the rest of the generated code, it expects the prelude to be in scope.
+pub fn request_inner(input: TokenStream) -> TokenStream {
+    let input = match syn::parse2::<RequestInput>(input) {
+        Ok(input) => input,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    let url = input.url;
+    let constructor = match input.method.to_string().as_str() {
+        "GET" => quote! { RequestBuilder::get(#url) },
+        "POST" => quote! { RequestBuilder::post(#url) },
+        "PUT" => quote! { RequestBuilder::put(#url) },
+        "PATCH" => quote! { RequestBuilder::patch(#url) },
+        "DELETE" => quote! { RequestBuilder::delete(#url) },
+        "HEAD" => quote! { RequestBuilder::head(#url) },
+        "OPTIONS" => quote! { RequestBuilder::options(#url) },
+        "TRACE" => quote! { RequestBuilder::trace(#url) },
+        other => quote! { RequestBuilder::custom(#other, #url) },
+    };
+
+    // Names the expansion binds are hygienic, so they never shadow the caller's variables used in later arguments.
+    let builder = Ident::new("builder", Span::mixed_site());
+    let (first, second) = (Ident::new("first", Span::mixed_site()), Ident::new("second", Span::mixed_site()));
+
+    // Each argument becomes a statement, so every value is evaluated exactly once, in order.
+    let mut steps = vec![];
+    for Arg { key, value } in input.args {
+        let step = match key.to_string().as_str() {
+            "bearer" => quote! { #builder.with_bearer_auth(::std::string::ToString::to_string(&(#value))) },
+            "basic" => quote! { match #value { (#first, #second) => #builder.set_basic_auth(#first, #second) } },
+            "header" => quote! { match #value { (#first, #second) => #builder.with_header(#first, #second) } },
+            "json" => quote! { #builder.with_json(serde_json::json!(#value)) },
+            "body" => quote! { #builder.with_body(#value) },
+            "timeout" => quote! { #builder.with_timeout(#value) },
+            "retry" => quote! { #builder.with_retry(#value) },
+            other => {
+                let message = format!("unknown request! argument `{other}`, expected one of bearer, basic, header, json, body, timeout, retry");
+                return syn::Error::new(key.span(), message).to_compile_error();
+            },
+        };
+        steps.push(quote! { let #builder = #step; });
+    }
+
+    quote! {
+        {
+            let #builder = #constructor;
+            #(#steps)*
+            #builder.call()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::request::*;
+
+    #[test]
+    fn test_request() {
+        let generated = request_inner(quote!(POST "https://api.x.com/v1/items", bearer = token, json = { "name": name },));
+        let compare = quote!(
+            {
+                let builder = RequestBuilder::post("https://api.x.com/v1/items");
+                let builder = builder.with_bearer_auth(::std::string::ToString::to_string(&(token)));
+                let builder = builder.with_json(serde_json::json!({ "name": name }));
+                builder.call()
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+
+        let generated = request_inner(quote!(PROPFIND url, header = ("Depth", "1")));
+        let compare = quote!(
+            {
+                let builder = RequestBuilder::custom("PROPFIND", url);
+                let builder = match ("Depth", "1") { (first, second) => builder.with_header(first, second) };
+                builder.call()
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+    }
+}
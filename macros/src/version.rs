@@ -0,0 +1,42 @@
+use proc_macro2::TokenStream;
+use syn::{ItemFn, LitInt};
+use quote::quote;
+
+/// Tells the host the function's version first thing, so it's recorded before any step runs.
+pub fn workflow_version_inner(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let version = match syn::parse2::<LitInt>(attr).and_then(|version| version.base10_parse::<u32>()) {
+        Ok(version) => version,
+        Err(err) => return syn::Error::new(err.span(), "expected a version number, e.g. #[workflow_version(2)]").to_compile_error(),
+    };
+    let mut input = match syn::parse2::<ItemFn>(input) {
+        Ok(input) => input,
+        Err(err) => return err.to_compile_error(),
+    };
+    input.block.stmts.insert(0, syn::parse_quote! { set_workflow_version(#version); });
+    quote! { #input }
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use crate::version::*;
+
+    #[test]
+    fn test_workflow_version() {
+        let generated = workflow_version_inner(quote!(2), quote!(
+            fn order(id: String) -> Resumable<()> {
+                ship(id)?;
+                Resumable::Ready(())
+            }
+        ));
+        let compare = quote!(
+            fn order(id: String) -> Resumable<()> {
+                set_workflow_version(2u32);
+                ship(id)?;
+                Resumable::Ready(())
+            }
+        );
+        assert_eq!(generated.to_string(), compare.to_string());
+        assert!(workflow_version_inner(quote!("two"), quote!(fn a() {})).to_string().contains("expected a version number"));
+    }
+}
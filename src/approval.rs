@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{Audience, PromptBuilder, Resumable};
+use crate::prompt::{send_group, withdraw_group, PromptKind, PromptReply};
+use crate::workflow::{await_signal, select_all, with_deadline, TimedOut};
+
+/// How many approvers must agree.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum ApprovalPolicy {
+    // A single approval is enough.
+    Any,
+    // Everyone must approve. A single rejection decides.
+    All,
+    // At least this many must approve.
+    Quorum(usize),
+}
+
+/// What an approver answered.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum VoteDecision {
+    Approved,
+    Rejected,
+    // The prompt was dismissed or expired. Counts as not approving.
+    NoResponse,
+}
+
+/// The answer of a single approver.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Vote {
+    approver: Audience,
+    decision: VoteDecision,
+    comment: Option<String>,
+
+    // Id on the host of the user who answered, which matters when the approver is a role or channel.
+    responder: Option<String>,
+
+    // Seconds since the unix epoch when the answer was submitted.
+    answered_at: Option<u64>,
+}
+
+impl Vote {
+    pub fn approver(&self) -> &Audience {
+        &self.approver
+    }
+    pub fn decision(&self) -> VoteDecision {
+        self.decision
+    }
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+    /// Who answered, unless the prompt was dismissed or expired.
+    pub fn responder(&self) -> Option<&str> {
+        self.responder.as_deref()
+    }
+    pub fn answered_at(&self) -> Option<u64> {
+        self.answered_at
+    }
+}
+
+/// The outcome of `request_approvals`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ApprovalResult {
+    approved: bool,
+
+    // Votes cast before the policy was decided. Approvers who hadn't answered by then are left out.
+    votes: Vec<Vote>,
+}
+
+impl ApprovalResult {
+    pub fn is_approved(&self) -> bool {
+        self.approved
+    }
+    pub fn votes(&self) -> &[Vote] {
+        &self.votes
+    }
+}
+
+/// Asks every approver to approve or reject `subject`, formatted as markdown, at the same time.
+/// Resumes as soon as `policy` is decided, withdrawing the prompts of approvers who haven't answered yet.
+/// Fails without asking anyone if there are no approvers, or the quorum is 0 or larger than the number of approvers.
+pub fn request_approvals<S: Into<String>>(subject: S, approvers: Vec<Audience>, policy: ApprovalPolicy) -> Resumable<Result<ApprovalResult, Error>> {
+    if let Err(err) = check_policy(policy, approvers.len()) {
+        return Resumable::Ready(Err(err));
+    }
+    let subject = subject.into();
+    let prompts: Vec<PromptBuilder> = approvers.iter()
+        .map(|approver| approval_form(&subject).audience(approver.clone()))
+        .collect();
+
+    let mut min_replies = 1;
+    loop {
+        let replies = match send_group(&prompts, min_replies)? {
+            Ok(replies) => replies,
+            Err(err) => return Resumable::Ready(Err(err)),
+        };
+        let votes: Vec<Option<Vote>> = approvers.iter().zip(replies)
+            .map(|(approver, reply)| reply.map(|reply| vote_from_reply(approver.clone(), reply)))
+            .collect();
+        let decisions: Vec<Option<VoteDecision>> = votes.iter().map(|vote| vote.as_ref().map(Vote::decision)).collect();
+        if let Some(approved) = decide(policy, &decisions) {
+            if decisions.contains(&None) {
+                withdraw_group(&prompts);
+            }
+            let votes = votes.into_iter().flatten().collect();
+            return Resumable::Ready(Ok(ApprovalResult { approved, votes }));
+        }
+        // Every pause waits for one more answer than we've seen.
+        min_replies = decisions.iter().flatten().count() + 1;
+    }
+}
+
+/// The outcome of `approve_or_escalate`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum EscalationResult {
+    // Someone answered at this level, counting the primary approver as level 0. The vote says who, and whether they approved.
+    Decided { level: usize, vote: Vote },
+    // No one answered before their level timed out.
+    TimedOut,
+}
+
+/// The signal that moves `approve_or_escalate` on to the next level right away, e.g. when the primary approver is out of office.
+pub const ESCALATE_SIGNAL: &str = "escalate";
+
+// A step of `select_all`, borrowing the prompts it shows.
+type EscalationStep<'a> = Box<dyn FnOnce() -> Resumable<Option<Result<Vec<Option<PromptReply>>, Error>>> + 'a>;
+
+/// Asks `primary` to approve or reject `subject`, formatted as markdown, and if they haven't answered within `per_level_timeout`,
+/// withdraws the prompt and asks the next approver in `escalation_chain`, and so on.
+/// The first answer decides; a dismissed or expired prompt, or an `ESCALATE_SIGNAL`, moves on to the next level early.
+pub fn approve_or_escalate<S: Into<String>>(subject: S, primary: Audience, escalation_chain: Vec<Audience>, per_level_timeout: Duration) -> Resumable<Result<EscalationResult, Error>> {
+    let subject = subject.into();
+    for (level, approver) in std::iter::once(primary).chain(escalation_chain).enumerate() {
+        let prompts = vec![approval_form(&subject).audience(approver.clone())];
+        let answered = with_deadline(per_level_timeout, || {
+            let steps: Vec<EscalationStep> = vec![
+                Box::new(|| send_group(&prompts, 1).map(Some)),
+                Box::new(|| await_signal::<Value, _>(ESCALATE_SIGNAL).map(|_| None)),
+            ];
+            select_all(steps).map(|(_, answered)| answered)
+        })?;
+        let reply = match answered {
+            Ok(Some(Ok(replies))) => replies.into_iter().next().flatten(),
+            Ok(Some(Err(err))) => return Resumable::Ready(Err(err)),
+            // Escalated or timed out.
+            Ok(None) | Err(TimedOut) => None,
+        };
+        match reply.map(|reply| vote_from_reply(approver, reply)) {
+            Some(vote) if vote.decision() != VoteDecision::NoResponse => {
+                return Resumable::Ready(Ok(EscalationResult::Decided { level, vote }));
+            },
+            Some(_) => {},
+            None => withdraw_group(&prompts),
+        }
+    }
+    Resumable::Ready(Ok(EscalationResult::TimedOut))
+}
+
+/// The form each approver sees. The host renders it as approve and reject buttons with a comment box.
+fn approval_form(subject: &str) -> PromptBuilder {
+    let schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "required": ["approved"],
+        "properties": {
+            "approved": { "type": "boolean" },
+            "comment": { "type": "string" },
+        },
+    });
+    let schema = serde_json::from_value(schema).expect("approval_form: invalid schema");
+    PromptBuilder::with_schema(schema)
+        .with_kind(PromptKind::Approval)
+        .markdown(subject)
+}
+
+fn vote_from_reply(approver: Audience, reply: PromptReply) -> Vote {
+    match reply {
+        PromptReply::Answered { value, responder, answered_at } => {
+            // A submission that can't be read counts as a rejection.
+            let value = value.value().unwrap_or_default();
+            let comment = value.get("comment").and_then(Value::as_str).filter(|c| !c.is_empty()).map(String::from);
+            let decision = match value.get("approved") {
+                Some(Value::Bool(true)) => VoteDecision::Approved,
+                _ => VoteDecision::Rejected,
+            };
+            Vote { approver, decision, comment, responder: Some(responder), answered_at: Some(answered_at) }
+        },
+        PromptReply::Cancelled | PromptReply::Expired => {
+            Vote { approver, decision: VoteDecision::NoResponse, comment: None, responder: None, answered_at: None }
+        },
+    }
+}
+
+/// Rejects policies that would be decided without asking anyone, or could never be met.
+fn check_policy(policy: ApprovalPolicy, approvers: usize) -> Result<(), Error> {
+    match policy {
+        _ if approvers == 0 => Err(Error::msg("An approval needs at least one approver")),
+        ApprovalPolicy::Quorum(0) => Err(Error::msg("A quorum must be at least 1")),
+        ApprovalPolicy::Quorum(n) if n > approvers => Err(Error::msg(format!("A quorum of {n} can't be met by {approvers} approvers"))),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the votes so far decide the policy, `None` meaning it's still open.
+/// `votes` holds one entry per approver, `None` for those who haven't answered.
+fn decide(policy: ApprovalPolicy, votes: &[Option<VoteDecision>]) -> Option<bool> {
+    let approvals = votes.iter().filter(|v| **v == Some(VoteDecision::Approved)).count();
+    let pending = votes.iter().filter(|v| v.is_none()).count();
+    let needed = match policy {
+        ApprovalPolicy::Any => 1,
+        ApprovalPolicy::All => votes.len(),
+        ApprovalPolicy::Quorum(n) => n,
+    };
+    if approvals >= needed {
+        Some(true)
+    } else if approvals + pending < needed {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::approval::*;
+    use VoteDecision::*;
+
+    #[test]
+    fn test_check_policy_without_approvers() {
+        assert!(check_policy(ApprovalPolicy::Any, 0).is_err());
+        assert!(check_policy(ApprovalPolicy::All, 0).is_err());
+        assert!(check_policy(ApprovalPolicy::All, 1).is_ok());
+    }
+
+    #[test]
+    fn test_check_policy_quorum_of_zero() {
+        assert!(check_policy(ApprovalPolicy::Quorum(0), 2).is_err());
+        assert!(check_policy(ApprovalPolicy::Quorum(1), 2).is_ok());
+    }
+
+    #[test]
+    fn test_check_policy_quorum_too_large() {
+        assert!(check_policy(ApprovalPolicy::Quorum(3), 2).is_err());
+        assert!(check_policy(ApprovalPolicy::Quorum(2), 2).is_ok());
+    }
+
+    #[test]
+    fn test_decide() {
+        assert_eq!(decide(ApprovalPolicy::Any, &[None, Some(Approved), None]), Some(true));
+        assert_eq!(decide(ApprovalPolicy::Any, &[Some(Rejected), None]), None);
+        assert_eq!(decide(ApprovalPolicy::Any, &[Some(Rejected), Some(NoResponse)]), Some(false));
+        assert_eq!(decide(ApprovalPolicy::All, &[Some(Approved), None]), None);
+        assert_eq!(decide(ApprovalPolicy::All, &[Some(Rejected), None]), Some(false));
+        assert_eq!(decide(ApprovalPolicy::All, &[Some(Approved), Some(Approved)]), Some(true));
+        assert_eq!(decide(ApprovalPolicy::Quorum(2), &[Some(Approved), Some(Rejected), None]), None);
+        assert_eq!(decide(ApprovalPolicy::Quorum(2), &[Some(Approved), Some(Rejected), Some(Rejected)]), Some(false));
+        assert_eq!(decide(ApprovalPolicy::Quorum(2), &[Some(Approved), None, Some(Approved)]), Some(true));
+    }
+}
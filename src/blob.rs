@@ -0,0 +1,53 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{value_to_host, vec_parts_from_host, value_from_host};
+use crate::{RequestBuilder, RequestError};
+
+/// A file kept in the host's blob store.
+/// Only this small handle lives in guest memory; it's serializable, so it can be passed between steps.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct BlobHandle {
+    // Identifier of the blob on the host.
+    id: String,
+
+    // Size of the blob in bytes.
+    size: u64,
+
+    // MIME type reported when the blob was stored, if any.
+    content_type: Option<String>,
+}
+
+impl BlobHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+#[derive(Deserialize)]
+struct HostRequestDownloadOut (Result<BlobHandle, RequestError>);
+
+impl RequestBuilder {
+    /// Makes the request and has the host stream the response body straight into its blob store.
+    /// Use this for large downloads that shouldn't be copied into guest memory.
+    /// Responses without a 2xx status fail and store nothing. Retry policies don't apply.
+    pub fn download_to_blob(&self) -> Result<BlobHandle, RequestError> {
+        self.validate()?;
+        let input = self.prepare()?;
+        let (offset, size) = value_to_host(&input);
+        let offset = unsafe { host_request_download(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: HostRequestDownloadOut = value_from_host(offset, size);
+        out.0
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_request_download(offset: u32, size: u32) -> u32;
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{HostRequestResponse, RequestBuilder, RequestError};
+use crate::request::HostRequestType;
+use crate::headers;
+
+/// A response kept around for revalidation, with the validators the server gave us.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct CacheEntry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    response: HostRequestResponse,
+}
+
+/// Makes conditional GET requests, reusing the previous body when the server answers 304 Not Modified.
+/// Only GET and HEAD requests are cached. The cache is serializable, so it can be kept across pauses
+/// in a multi-step function that polls the same endpoint.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CachedClient {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl CachedClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the request, sending If-None-Match and If-Modified-Since when we've seen this URL before.
+    /// A 304 response is swapped for the cached response.
+    pub fn call(&mut self, request: RequestBuilder) -> Result<HostRequestResponse, RequestError> {
+        if !matches!(request.method(), HostRequestType::Get | HostRequestType::Head) {
+            return request.call();
+        }
+        let key = format!("{} {}", request.method_str(), request.url());
+
+        let mut request = request;
+        if let Some(entry) = self.entries.get(&key) {
+            if let Some(etag) = &entry.etag {
+                request = request.with_raw_header(headers::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.with_raw_header(headers::IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let response = request.call()?;
+        if response.code() == 304 {
+            if let Some(entry) = self.entries.get(&key) {
+                return Ok(entry.response.clone());
+            }
+        }
+
+        let etag = response.header(headers::ETAG).map(str::to_string);
+        let last_modified = response.header(headers::LAST_MODIFIED).map(str::to_string);
+        if response.code() == 200 && (etag.is_some() || last_modified.is_some()) {
+            self.entries.insert(key, CacheEntry { etag, last_modified, response: response.clone() });
+        }
+        Ok(response)
+    }
+
+    /// Forgets every cached response.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+
+use crate::{metrics, replay, workflow, Resumable};
+
+thread_local! {
+    // Hooks registered by the current call of a multistep function, in the order they were registered.
+    static CANCEL_HOOKS: RefCell<Vec<Box<dyn FnOnce()>>> = RefCell::new(vec![]);
+}
+
+/// Registers cleanup to run if the run is cancelled, e.g. releasing a lock or voiding a hold on a card.
+/// Register it right after taking what it cleans up; replays register it again, so it only runs once.
+/// Hooks run in reverse order of registration, when the host cancels a paused run or the function returns `Resumable::Cancelled`.
+pub fn on_cancel<F: FnOnce() + 'static>(hook: F) {
+    CANCEL_HOOKS.with(|hooks| hooks.borrow_mut().push(Box::new(hook)));
+}
+
+/// Whether the host is cancelling the run. While it is, calls that would pause make the function pause instead,
+/// after which its cancellation hooks run.
+pub fn is_cancelling() -> bool {
+    unsafe { host_is_cancelling() != 0 }
+}
+
+/// Runs the cancellation hooks if the run is being cancelled, reports how long the call took,
+/// and clears what was tracked for this call, like replay recording.
+/// Called by the code `#[middle_multistep_fn]` generates.
+#[doc(hidden)]
+pub fn finish_multistep<T>(output: Resumable<T>) -> Resumable<T> {
+    replay::disable();
+    workflow::reset_call_counters();
+    metrics::finish_call();
+    let hooks = CANCEL_HOOKS.with(|hooks| hooks.take());
+    let cancelled = match output {
+        Resumable::Cancelled => true,
+        Resumable::Pause => is_cancelling(),
+        Resumable::Ready(_) => false,
+    };
+    if !cancelled {
+        return output;
+    }
+    for hook in hooks.into_iter().rev() {
+        hook();
+    }
+    Resumable::Cancelled
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_is_cancelling() -> u32;
+}
@@ -0,0 +1,81 @@
+/// The characters windows-1252 puts at 0x80 to 0x9F, where ISO-8859-1 has control characters.
+/// Browsers decode ISO-8859-1 labelled text as windows-1252, since that's what servers almost always mean.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Charset {
+    Utf8,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Charset {
+    fn from_label(label: &str) -> Option<Self> {
+        match label.trim().trim_matches('"').to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Charset::Utf8),
+            "iso-8859-1" | "iso8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" | "windows-1252" | "cp1252" => Some(Charset::Windows1252),
+            "utf-16" | "utf-16le" => Some(Charset::Utf16Le),
+            "utf-16be" => Some(Charset::Utf16Be),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the charset parameter of a Content-Type header value.
+pub(crate) fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim().eq_ignore_ascii_case("charset").then_some(value.trim())
+    })
+}
+
+/// Decodes text in the given charset. A byte order mark wins over the label, as browsers do.
+/// Unknown charsets are decoded as UTF-8, replacing invalid sequences.
+pub(crate) fn decode(bytes: &[u8], charset: Option<&str>) -> String {
+    let (charset, bytes) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Charset::Utf8, rest),
+        [0xFF, 0xFE, rest @ ..] => (Charset::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (Charset::Utf16Be, rest),
+        _ => (charset.and_then(Charset::from_label).unwrap_or(Charset::Utf8), bytes),
+    };
+    match charset {
+        Charset::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Charset::Windows1252 => bytes.iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                _ => b as char,
+            })
+            .collect(),
+        Charset::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+        Charset::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks(2).map(|pair| match pair {
+        [a, b] => unit([*a, *b]),
+        // A dangling odd byte can't be decoded.
+        _ => 0xFFFD,
+    });
+    char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::charset::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(charset_param("text/html; charset=\"ISO-8859-1\""), Some("\"ISO-8859-1\""));
+        assert_eq!(decode(b"caf\xE9 \x80", Some("\"ISO-8859-1\"")), "café €");
+        assert_eq!(decode(b"\xFF\xFEh\x00i\x00", Some("iso-8859-1")), "hi");
+        assert_eq!(decode(b"\x00h\x00i", Some("UTF-16BE")), "hi");
+        assert_eq!(decode("café".as_bytes(), None), "café");
+    }
+}
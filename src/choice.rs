@@ -0,0 +1,112 @@
+use anyhow::Error;
+use schemars::schema::RootSchema;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{Resumable, PromptBuilder, PromptOutcome, SchemaBuilder, Widget};
+use crate::validate::FieldErrors;
+
+// Name of the single field of a select form.
+const SELECTION: &str = "selection";
+
+/// One of the options of a select prompt.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Choice {
+    // Identifies the option. This is what the host sends back.
+    value: String,
+
+    // Text shown to the user.
+    label: String,
+
+    // Longer explanation shown under the label.
+    description: Option<String>,
+}
+
+impl Choice {
+    pub fn new<S: Into<String>, S1: Into<String>>(value: S, label: S1) -> Self {
+        Self { value: value.into(), label: label.into(), description: None }
+    }
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Asks the user to pick one of `options`, which may be built at runtime, e.g. from the response of an earlier request.
+/// Without any options there's nothing to pick, so the outcome is an error and nothing is shown.
+pub fn prompt_select<S: Into<String>>(label: S, options: Vec<Choice>) -> Resumable<PromptOutcome<Choice>> {
+    let label = label.into();
+    if options.is_empty() {
+        return Resumable::Ready(PromptOutcome::Error(Error::msg(format!("No options to pick from for {label}"))));
+    }
+    PromptBuilder::with_schema(select_schema(label, &options, false))
+        .widget(SELECTION, Widget::Select)
+        .prompt_until_valid(|value| {
+            let picked = value.get(SELECTION).and_then(Value::as_str).and_then(|picked| find(&options, picked));
+            picked.cloned().ok_or_else(|| selection_error("Pick one of the options"))
+        })
+}
+
+/// Asks the user to pick any number of `options`.
+pub fn prompt_multi_select<S: Into<String>>(label: S, options: Vec<Choice>) -> Resumable<PromptOutcome<Vec<Choice>>> {
+    PromptBuilder::with_schema(select_schema(label.into(), &options, true))
+        .widget(SELECTION, Widget::MultiSelect)
+        .prompt_until_valid(|value| {
+            let picked = match value.get(SELECTION) {
+                Some(Value::Array(picked)) => picked,
+                None | Some(Value::Null) => return Ok(vec![]),
+                Some(_) => return Err(selection_error("Pick from the options")),
+            };
+            picked.iter()
+                .map(|picked| picked.as_str().and_then(|picked| find(&options, picked)).cloned())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| selection_error("Pick from the options"))
+        })
+}
+
+fn find<'a>(options: &'a [Choice], value: &str) -> Option<&'a Choice> {
+    options.iter().find(|option| option.value == value)
+}
+
+fn selection_error(message: &str) -> FieldErrors {
+    FieldErrors::from([(SELECTION.to_string(), vec![message.to_string()])])
+}
+
+/// Builds the schema of a form with a single field holding one, or with `multiple` any number, of the options.
+fn select_schema(label: String, options: &[Choice], multiple: bool) -> RootSchema {
+    if multiple {
+        SchemaBuilder::new().multi_select(SELECTION, options).label(label).build()
+    } else {
+        SchemaBuilder::new().select(SELECTION, options).label(label).required().build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::choice::*;
+
+    #[test]
+    fn test_select_schema() {
+        let options = vec![Choice::new("eu", "Europe"), Choice::new("us", "United States").with_description("Virginia")];
+        let schema = serde_json::to_value(select_schema("Region".to_string(), &options, false)).unwrap();
+        let selection = &schema["properties"]["selection"];
+        assert_eq!(selection["title"], "Region");
+        assert_eq!(selection["oneOf"][1]["const"], "us");
+        assert_eq!(selection["oneOf"][1]["title"], "United States");
+        assert_eq!(schema["required"][0], "selection");
+
+        let schema = serde_json::to_value(select_schema("Regions".to_string(), &options, true)).unwrap();
+        assert_eq!(schema["properties"]["selection"]["items"]["oneOf"][0]["const"], "eu");
+        assert_eq!(find(&options, "eu"), Some(&options[0]));
+        assert_eq!(find(&options, "ap"), None);
+    }
+}
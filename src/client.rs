@@ -0,0 +1,220 @@
+use std::{cell::OnceCell, fmt};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{RequestBuilder, HostRequestType, RequestError, value_to_host, vec_parts_from_host, value_from_host};
+use crate::ids::new_uuid;
+
+/// Makes requests that share connections on the host.
+/// Every request built by the same client carries the same connection pool key, so repeated calls to one API
+/// within an instantiation skip the TCP and TLS handshakes after the first.
+/// The client is serializable, so it can be kept across pauses in a multi-step function.
+/// Credentials are left out; a client made with `from_host_config` looks them up from the host again when needed.
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct Client {
+    // Key the host pools this client's connections under.
+    pool_key: String,
+
+    // URL that relative paths are appended to.
+    base_url: Option<String>,
+
+    // Headers added to every request, e.g. an API version.
+    default_headers: Vec<(String, String)>,
+
+    // Name of the host connection the client was made from, which its credentials are looked up under.
+    connection: Option<String>,
+
+    // Credentials added to every request. Kept out of the serialized client, so they don't end up in the run's state.
+    #[serde(skip)]
+    credentials: OnceCell<Credentials>,
+}
+
+#[derive(PartialEq, Clone, Default)]
+struct Credentials {
+    bearer_auth: Option<String>,
+    basic_auth: Option<(String, String)>,
+}
+
+/// A named connection configured on the host, such as "crm" pointing at either staging or production.
+#[derive(Deserialize)]
+struct HostConnection {
+    base_url: Option<String>,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    bearer_auth: Option<String>,
+    basic_auth: Option<(String, String)>,
+}
+
+#[derive(Deserialize)]
+struct HostGetConnectionOut (Result<HostConnection, RequestError>);
+
+impl fmt::Debug for Client {
+    // Never print credentials.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("pool_key", &self.pool_key)
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Creates a client with a pool of its own.
+    pub fn new() -> Self {
+        Self::with_pool_key(new_uuid())
+    }
+    /// Creates a client that pools its connections under `key`. Clients with the same key share connections.
+    pub fn with_pool_key<S: Into<String>>(key: S) -> Self {
+        Self {
+            pool_key: key.into(),
+            base_url: None,
+            default_headers: vec![],
+            connection: None,
+            credentials: OnceCell::new(),
+        }
+    }
+    /// Creates a client for a connection configured on the host, taking its base URL, default headers and credentials.
+    /// The same function can then run against staging and production without hardcoding either.
+    pub fn from_host_config<S: Into<String>>(name: S) -> Result<Self, RequestError> {
+        let name: String = name.into();
+        let connection = host_connection(&name)?;
+        Ok(Self {
+            pool_key: format!("connection:{name}"),
+            base_url: connection.base_url,
+            default_headers: connection.headers,
+            connection: Some(name),
+            credentials: OnceCell::from(Credentials { bearer_auth: connection.bearer_auth, basic_auth: connection.basic_auth }),
+        })
+    }
+    /// Resolves relative paths against `url`. See `url`.
+    pub fn with_base_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+    /// Adds a header to every request made through this client.
+    pub fn with_default_header<S: Into<String>, S1: Into<String>>(mut self, name: S, value: S1) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+    pub fn pool_key(&self) -> &str {
+        &self.pool_key
+    }
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// Resolves a path against the base URL. Paths are appended to it, so with a base URL of
+    /// `https://api.x.com/v1`, both `items` and `/items` become `https://api.x.com/v1/items`.
+    /// Absolute URLs, and anything without a base URL, are used as they are.
+    pub fn url(&self, path: &str) -> String {
+        match &self.base_url {
+            Some(base) if !has_scheme(path) => {
+                format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+            },
+            _ => path.to_string(),
+        }
+    }
+
+    /// Creates a request that uses this client's connections, base URL, default headers and credentials.
+    pub fn request<S: AsRef<str>>(&self, path: S, method: HostRequestType) -> RequestBuilder {
+        let builder = RequestBuilder::new(self.url(path.as_ref()), method)
+            .with_connection_pool_key(self.pool_key.clone())
+            .with_headers(self.default_headers.clone());
+        let credentials = match self.credentials() {
+            Ok(credentials) => credentials,
+            Err(err) => return builder.with_error(err),
+        };
+        let builder = match &credentials.bearer_auth {
+            Some(token) => builder.with_bearer_auth(token.clone()),
+            None => builder,
+        };
+        match &credentials.basic_auth {
+            Some((username, password)) => builder.set_basic_auth(username.clone(), password.clone()),
+            None => builder,
+        }
+    }
+    /// The credentials to send, looked up from the host connection the first time they're needed after the client was deserialized.
+    fn credentials(&self) -> Result<&Credentials, RequestError> {
+        if let Some(credentials) = self.credentials.get() {
+            return Ok(credentials);
+        }
+        let credentials = match &self.connection {
+            Some(name) => {
+                let connection = host_connection(name)?;
+                Credentials { bearer_auth: connection.bearer_auth, basic_auth: connection.basic_auth }
+            },
+            None => Credentials::default(),
+        };
+        Ok(self.credentials.get_or_init(|| credentials))
+    }
+    pub fn get<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Get)
+    }
+    pub fn post<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Post)
+    }
+    pub fn put<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Put)
+    }
+    pub fn patch<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Patch)
+    }
+    pub fn delete<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Delete)
+    }
+    pub fn head<S: AsRef<str>>(&self, path: S) -> RequestBuilder {
+        self.request(path, HostRequestType::Head)
+    }
+}
+
+fn host_connection(name: &str) -> Result<HostConnection, RequestError> {
+    let (offset, size) = value_to_host(&name);
+    let offset = unsafe { host_get_connection(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: HostGetConnectionOut = value_from_host(offset, size);
+    out.0
+}
+
+/// Whether `path` starts with a scheme such as `https://`, rather than merely containing `://`, e.g. in its query.
+fn has_scheme(path: &str) -> bool {
+    match path.split_once("://") {
+        Some((scheme, _)) => scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')),
+        None => false,
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_get_connection(offset: u32, size: u32) -> u32;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::*;
+
+    #[test]
+    fn test_url() {
+        let client = Client::with_pool_key("test").with_base_url("https://api.x.com/v1/");
+        assert_eq!(client.url("/items"), "https://api.x.com/v1/items");
+        assert_eq!(client.url("items?page=2"), "https://api.x.com/v1/items?page=2");
+        assert_eq!(client.url("https://other.com/a"), "https://other.com/a");
+        assert_eq!(Client::with_pool_key("test").url("https://x.com"), "https://x.com");
+        assert_eq!(client.url("redirect?to=https://other.com"), "https://api.x.com/v1/redirect?to=https://other.com");
+        assert_eq!(client.url("/search?q=a://b"), "https://api.x.com/v1/search?q=a://b");
+    }
+
+    #[test]
+    fn test_credentials_not_serialized() {
+        let mut client = Client::with_pool_key("test");
+        client.credentials = OnceCell::from(Credentials { bearer_auth: Some("s3cret-token".to_string()), basic_auth: None });
+        let serialized = serde_json::to_string(&client).unwrap();
+        assert!(!serialized.contains("s3cret-token"));
+    }
+}
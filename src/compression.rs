@@ -0,0 +1,46 @@
+use std::io::Read;
+
+use serde::{Serialize, Deserialize};
+
+/// Content encodings the guest knows how to undo.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// The token used for this encoding in Accept-Encoding and Content-Encoding headers.
+    pub fn token(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+}
+
+/// Decompresses a response body.
+/// Stops after `limit` bytes plus one, so a tiny body that inflates enormously can't exhaust guest memory;
+/// a result longer than `limit` means the body was cut short.
+pub fn decompress(encoding: ContentEncoding, bytes: &[u8], limit: u64) -> Result<Vec<u8>, String> {
+    let limit = limit.saturating_add(1);
+    let mut out = vec![];
+    let result = match encoding {
+        ContentEncoding::Gzip => flate2::read::MultiGzDecoder::new(bytes).take(limit).read_to_end(&mut out),
+        // Servers disagree on whether "deflate" means zlib-wrapped or raw deflate, so accept both.
+        ContentEncoding::Deflate => match flate2::read::ZlibDecoder::new(bytes).take(limit).read_to_end(&mut out) {
+            Ok(len) => Ok(len),
+            Err(_) => {
+                out.clear();
+                flate2::read::DeflateDecoder::new(bytes).take(limit).read_to_end(&mut out)
+            },
+        },
+        ContentEncoding::Brotli => brotli_decompressor::Decompressor::new(bytes, 4096).take(limit).read_to_end(&mut out),
+    };
+    match result {
+        Ok(_) => Ok(out),
+        Err(err) => Err(format!("Unable to decompress {} response body: {err}", encoding.token())),
+    }
+}
@@ -0,0 +1,50 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{vec_parts_from_host, value_from_host, Timestamp};
+
+/// What the host knows about the current run, e.g. for idempotency keys, correlating logs or audit trails.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RunContext {
+    // Stays the same across the pauses and replays of a multistep function.
+    run_id: String,
+    function_name: String,
+    // Counts from 1, and goes up when the host retries the run after a failure.
+    attempt: u32,
+    started_at: Timestamp,
+    // The user who started the run, or `None` when it was started by a schedule, a webhook or another function.
+    invoker: Option<String>,
+}
+
+impl RunContext {
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+    pub fn started_at(&self) -> Timestamp {
+        self.started_at
+    }
+    pub fn invoker(&self) -> Option<&str> {
+        self.invoker.as_deref()
+    }
+}
+
+#[derive(Deserialize)]
+struct RunContextOut (RunContext);
+
+/// The context of the current run.
+pub fn context() -> RunContext {
+    let offset = unsafe { host_run_context() };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: RunContextOut = value_from_host(offset, size);
+    out.0
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_run_context() -> u32;
+}
@@ -0,0 +1,208 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{HostRequestResponse, RequestBuilder};
+use crate::url::Url;
+use crate::headers;
+use crate::datetime::{now, parse_http_date};
+
+/// A single cookie captured from a `Set-Cookie` header.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Cookie {
+    name: String,
+    value: String,
+
+    // Domain the cookie is sent to. Without a Domain attribute, only the exact host that set it matches.
+    domain: String,
+    host_only: bool,
+
+    // Path prefix the cookie is sent to.
+    path: String,
+
+    // Only send over https.
+    secure: bool,
+
+    // Seconds since the unix epoch after which the cookie is dropped. Session cookies never expire.
+    expires_at: Option<u64>,
+}
+
+impl Cookie {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+    fn matches(&self, secure: bool, host: &str, path: &str) -> bool {
+        if self.secure && !secure {
+            return false;
+        }
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            host == self.domain || host.ends_with(&format!(".{}", self.domain))
+        };
+        let path_matches = path == self.path
+            || (path.starts_with(&self.path) && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+        domain_matches && path_matches
+    }
+}
+
+/// Stores cookies set by responses and sends them along with later requests.
+/// The jar is serializable, so it can be kept around across pauses in a multi-step function.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Captures every `Set-Cookie` header of a response to a request made to `url`.
+    pub fn store(&mut self, url: &str, response: &HostRequestResponse) {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        let (host, path) = (url.host(), url.path());
+        let now = now();
+        for value in response.headers().get_all(headers::SET_COOKIE) {
+            let cookie = match parse_set_cookie(value, host, path, now) {
+                Some(cookie) => cookie,
+                None => continue,
+            };
+            // A newer cookie with the same name, domain and path replaces the old one.
+            self.cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+            if !cookie.is_expired(now) {
+                self.cookies.push(cookie);
+            }
+        }
+    }
+
+    /// Returns the cookies that should be sent with a request to `url`.
+    pub fn cookies_for(&self, url: &str) -> Vec<&Cookie> {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return vec![],
+        };
+        let now = now();
+        self.cookies.iter()
+            .filter(|c| !c.is_expired(now) && c.matches(url.is_secure(), url.host(), url.path()))
+            .collect()
+    }
+
+    /// Builds the value of the `Cookie` header for a request to `url`, if any cookie applies.
+    pub fn header_for(&self, url: &str) -> Option<String> {
+        let cookies = self.cookies_for(url);
+        if cookies.is_empty() {
+            return None;
+        }
+        let pairs: Vec<_> = cookies.iter().map(|c| format!("{}={}", c.name, c.value)).collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Removes expired cookies.
+    pub fn clear_expired(&mut self) {
+        let now = now();
+        self.cookies.retain(|c| !c.is_expired(now));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+}
+
+impl RequestBuilder {
+    /// Sends along the cookies from the jar that match this request's URL.
+    pub fn with_cookies(self, jar: &CookieJar) -> Self {
+        match jar.header_for(self.url()) {
+            Some(header) => self.with_raw_header(headers::COOKIE, header),
+            None => self,
+        }
+    }
+}
+
+/// Parses a `Set-Cookie` header value received from `host` for a request to `request_path`.
+fn parse_set_cookie(header: &str, host: &str, request_path: &str, now: u64) -> Option<Cookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    // The default path is the request path up to, but not including, its last slash.
+    let default_path = match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(idx) => request_path[..idx].to_string(),
+    };
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+        domain: host.to_string(),
+        host_only: true,
+        path: default_path,
+        secure: false,
+        expires_at: None,
+    };
+    let mut max_age = None;
+
+    for attr in parts {
+        let (key, val) = match attr.split_once('=') {
+            Some((key, val)) => (key.trim(), val.trim()),
+            None => (attr.trim(), ""),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "domain" => {
+                let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                // Servers may only set cookies for their own domain or a parent of it.
+                if !(host == domain || host.ends_with(&format!(".{domain}"))) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            },
+            "path" if val.starts_with('/') => cookie.path = val.to_string(),
+            "secure" => cookie.secure = true,
+            "max-age" => max_age = val.parse::<i64>().ok(),
+            "expires" => {
+                if let Some(expires_at) = parse_http_date(val) {
+                    cookie.expires_at = Some(expires_at);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // Max-Age wins over Expires.
+    if let Some(max_age) = max_age {
+        cookie.expires_at = Some(if max_age <= 0 { 0 } else { now + max_age as u64 });
+    }
+    Some(cookie)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cookie::*;
+
+    #[test]
+    fn test_cookie_matching() {
+        let cookie = parse_set_cookie("sid=abc; Domain=.example.com; Path=/api; Secure", "login.example.com", "/login", 0).unwrap();
+        assert!(cookie.matches(true, "api.example.com", "/api/items"));
+        assert!(!cookie.matches(false, "api.example.com", "/api/items"));
+        assert!(!cookie.matches(true, "api.example.com", "/apix"));
+        assert!(!cookie.matches(true, "example.org", "/api"));
+
+        let host_only = parse_set_cookie("a=b", "example.com", "/x/y", 0).unwrap();
+        assert!(host_only.matches(false, "example.com", "/x/z"));
+        assert!(!host_only.matches(false, "sub.example.com", "/x"));
+
+        assert!(parse_set_cookie("a=b; Domain=other.com", "example.com", "/", 0).is_none());
+        assert_eq!(parse_set_cookie("a=b; Max-Age=60", "example.com", "/", 100).unwrap().expires_at, Some(160));
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch, according to the host's clock.
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Milliseconds since the unix epoch, according to the host's clock.
+pub(crate) fn now_millis() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0)
+}
+
+/// Parses an HTTP date such as `Wed, 21 Oct 2015 07:28:00 GMT` into seconds since the unix epoch.
+/// Also accepts the dashed `21-Oct-2015` form many servers still send.
+pub(crate) fn parse_http_date(date: &str) -> Option<u64> {
+    let date = date.split_once(',').map(|(_, rest)| rest).unwrap_or(date);
+    let mut fields = date.split([' ', '-']).filter(|f| !f.is_empty());
+    let day: u64 = fields.next()?.parse().ok()?;
+    let month = match fields.next()?.to_ascii_lowercase().as_str() {
+        "jan" => 1, "feb" => 2, "mar" => 3, "apr" => 4, "may" => 5, "jun" => 6,
+        "jul" => 7, "aug" => 8, "sep" => 9, "oct" => 10, "nov" => 11, "dec" => 12,
+        _ => return None,
+    };
+    let mut year: i64 = fields.next()?.parse().ok()?;
+    if year < 100 {
+        year += if year < 70 { 2000 } else { 1900 };
+    }
+    let mut time = fields.next()?.split(':').map(|f| f.parse::<u64>());
+    let (h, m, s) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    let days = days_from_civil(year, month, day)?;
+    Some(days * 86400 + h * 3600 + m * 60 + s)
+}
+
+/// Number of days between the unix epoch and the given date.
+pub(crate) fn days_from_civil(year: i64, month: u64, day: u64) -> Option<u64> {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days).ok()
+}
+
+/// The (year, month, day) of the given number of days since the unix epoch.
+pub(crate) fn civil_from_days(days: u64) -> (i64, u64, u64) {
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The (year, month, day, hour, minute, second) of a unix timestamp, in UTC.
+pub(crate) fn utc_from_timestamp(timestamp: u64) -> (i64, u64, u64, u64, u64, u64) {
+    let (year, month, day) = civil_from_days(timestamp / 86400);
+    let secs = timestamp % 86400;
+    (year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datetime::*;
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(1445412480));
+        assert_eq!(parse_http_date("Wed, 21-Oct-2015 07:28:00 GMT"), Some(1445412480));
+        assert_eq!(parse_http_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_civil_roundtrip() {
+        assert_eq!(utc_from_timestamp(1445412480), (2015, 10, 21, 7, 28, 0));
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29).unwrap()), (2024, 2, 29));
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+    }
+}
@@ -0,0 +1,146 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{BlobHandle, PromptBuilder, PromptOutcome, Resumable};
+use crate::prompt::PromptKind;
+use crate::validate::FieldErrors;
+
+/// Where the contents of an uploaded file are.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum FileContent {
+    // Small files are handed over directly.
+    Inline(#[serde(with = "serde_bytes")] Vec<u8>),
+    // Larger files stay in the host's blob store.
+    Blob(BlobHandle),
+}
+
+/// A file the user uploaded in answer to `prompt_file`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct FileUpload {
+    filename: String,
+    content_type: String,
+
+    // Size of the file in bytes.
+    size: u64,
+
+    content: FileContent,
+}
+
+impl FileUpload {
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn content(&self) -> &FileContent {
+        &self.content
+    }
+    /// The contents of the file, if the host handed them over directly.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.content {
+            FileContent::Inline(bytes) => Some(bytes),
+            FileContent::Blob(_) => None,
+        }
+    }
+    /// The blob holding the file, if the host kept it in its blob store.
+    pub fn blob(&self) -> Option<&BlobHandle> {
+        match &self.content {
+            FileContent::Inline(_) => None,
+            FileContent::Blob(blob) => Some(blob),
+        }
+    }
+}
+
+// How the host describes an upload inside the submitted form. Inline contents are base64 encoded.
+#[derive(Deserialize)]
+struct UploadOut {
+    filename: String,
+    content_type: String,
+    size: u64,
+    data: Option<String>,
+    blob: Option<BlobHandle>,
+}
+
+/// Asks the user to upload a file, e.g. a spreadsheet to import.
+/// `accepted_types` holds MIME types like "text/csv" or "image/*", or extensions like ".xlsx"; an empty list accepts anything.
+/// Files larger than `max_size` bytes are refused.
+pub fn prompt_file(accepted_types: &[&str], max_size: u64) -> Resumable<PromptOutcome<FileUpload>> {
+    let accepted_types: Vec<String> = accepted_types.iter().map(|t| t.to_string()).collect();
+    let schema = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "required": ["file"],
+        "properties": { "file": { "type": "object" } },
+    });
+    let schema = serde_json::from_value(schema).expect("prompt_file: invalid schema");
+    PromptBuilder::with_schema(schema)
+        .with_kind(PromptKind::File { accepted_types: accepted_types.clone(), max_size })
+        .prompt_until_valid(|value| {
+            let upload = parse_upload(value).map_err(file_error)?;
+            if !accepts(&accepted_types, &upload.filename, &upload.content_type) {
+                return Err(file_error(format!("Files of type {} aren't accepted", upload.content_type)));
+            }
+            if upload.size > max_size {
+                return Err(file_error(format!("The file is larger than {max_size} bytes")));
+            }
+            Ok(upload)
+        })
+}
+
+fn parse_upload(mut value: Value) -> Result<FileUpload, String> {
+    let out: UploadOut = serde_json::from_value(value["file"].take()).map_err(|_| "Upload a file".to_string())?;
+    let content = match (out.data, out.blob) {
+        (Some(data), _) => FileContent::Inline(STANDARD.decode(data).map_err(|_| "The upload was corrupted".to_string())?),
+        (None, Some(blob)) => FileContent::Blob(blob),
+        (None, None) => return Err("Upload a file".to_string()),
+    };
+    Ok(FileUpload { filename: out.filename, content_type: out.content_type, size: out.size, content })
+}
+
+fn file_error<S: Into<String>>(message: S) -> FieldErrors {
+    FieldErrors::from([("file".to_string(), vec![message.into()])])
+}
+
+/// Whether a file matches one of the accepted types, the way an HTML `accept` attribute does.
+fn accepts(accepted_types: &[String], filename: &str, content_type: &str) -> bool {
+    if accepted_types.is_empty() {
+        return true;
+    }
+    let filename = filename.to_ascii_lowercase();
+    let content_type = content_type.to_ascii_lowercase();
+    accepted_types.iter().any(|accepted| {
+        let accepted = accepted.to_ascii_lowercase();
+        if accepted.starts_with('.') {
+            filename.ends_with(&accepted)
+        } else if let Some(major) = accepted.strip_suffix("/*") {
+            content_type.split('/').next() == Some(major)
+        } else {
+            content_type == accepted
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::*;
+
+    #[test]
+    fn test_upload() {
+        let accepted = vec!["image/*".to_string(), ".xlsx".to_string(), "text/csv".to_string()];
+        assert!(accepts(&accepted, "cat.PNG", "image/png"));
+        assert!(accepts(&accepted, "Report.XLSX", "application/octet-stream"));
+        assert!(accepts(&accepted, "rows.txt", "text/csv"));
+        assert!(!accepts(&accepted, "notes.txt", "text/plain"));
+        assert!(accepts(&[], "notes.txt", "text/plain"));
+
+        let value = json!({ "file": { "filename": "a.csv", "content_type": "text/csv", "size": 3, "data": "YSxi" } });
+        assert_eq!(parse_upload(value).unwrap().bytes(), Some(&b"a,b"[..]));
+        assert!(parse_upload(json!({})).is_err());
+    }
+}
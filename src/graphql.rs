@@ -0,0 +1,143 @@
+use std::fmt;
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{RequestBuilder, RequestError};
+
+/// A GraphQL query or mutation sent over HTTP POST.
+pub struct GraphQlRequest {
+    endpoint: String,
+    query: String,
+    variables: Option<Value>,
+    operation_name: Option<String>,
+    bearer_auth: Option<String>,
+}
+
+/// The standard body of a GraphQL POST request.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlBody<'a> {
+    query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation_name: Option<&'a str>,
+}
+
+/// A single entry of the `errors` array of a GraphQL response.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct GraphQlErrorItem {
+    pub message: String,
+    #[serde(default)]
+    pub path: Option<Vec<Value>>,
+    #[serde(default)]
+    pub extensions: Option<Value>,
+}
+
+/// The raw response of a GraphQL server. A server may return partial data alongside errors.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct GraphQlResponse {
+    #[serde(default)]
+    pub data: Option<Value>,
+    #[serde(default)]
+    pub errors: Vec<GraphQlErrorItem>,
+}
+
+/// Why a GraphQL call failed.
+#[derive(Debug)]
+pub enum GraphQlError {
+    // The request never got a response.
+    Transport(RequestError),
+
+    // The server answered with a status that isn't 2xx, and no GraphQL errors to explain it.
+    Status(u32, String),
+
+    // The response wasn't a GraphQL response, or `data` didn't match the expected type.
+    Decode(String),
+
+    // The server resolved the query but reported errors. Any partial data is kept.
+    GraphQl { errors: Vec<GraphQlErrorItem>, data: Option<Value> },
+}
+
+impl fmt::Display for GraphQlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphQlError::Transport(err) => write!(f, "GraphQL request failed: {err}"),
+            GraphQlError::Status(code, body) => write!(f, "GraphQL server returned HTTP {code}: {body}"),
+            GraphQlError::Decode(err) => write!(f, "Unable to decode GraphQL response: {err}"),
+            GraphQlError::GraphQl { errors, .. } => {
+                let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+                write!(f, "GraphQL errors: {}", messages.join("; "))
+            },
+        }
+    }
+}
+
+impl std::error::Error for GraphQlError {}
+
+impl GraphQlRequest {
+    pub fn new<S: Into<String>>(endpoint: S) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            query: String::new(),
+            variables: None,
+            operation_name: None,
+            bearer_auth: None,
+        }
+    }
+    pub fn query<S: Into<String>>(mut self, query: S) -> Self {
+        self.query = query.into();
+        self
+    }
+    pub fn variables(mut self, variables: Value) -> Self {
+        self.variables = Some(variables);
+        self
+    }
+    /// Picks which operation to run when the query document defines several.
+    pub fn operation_name<S: Into<String>>(mut self, operation_name: S) -> Self {
+        self.operation_name = Some(operation_name.into());
+        self
+    }
+    pub fn with_bearer_auth<S: Into<String>>(mut self, bearer_token: S) -> Self {
+        self.bearer_auth = Some(bearer_token.into());
+        self
+    }
+
+    /// Builds the HTTP request, for callers that need to tweak it further before calling it.
+    pub fn to_request(&self) -> RequestBuilder {
+        let body = GraphQlBody {
+            query: &self.query,
+            variables: self.variables.as_ref(),
+            operation_name: self.operation_name.as_deref(),
+        };
+        let body = serde_json::to_value(body).expect("GraphQL body could not be serialized into JSON");
+        let builder = RequestBuilder::post(self.endpoint.clone()).with_json(body);
+        match &self.bearer_auth {
+            Some(token) => builder.with_bearer_auth(token.clone()),
+            None => builder,
+        }
+    }
+
+    /// Runs the query and returns the raw response, including any GraphQL errors.
+    pub fn send_raw(&self) -> Result<GraphQlResponse, GraphQlError> {
+        let response = self.to_request().call().map_err(GraphQlError::Transport)?;
+        let code = response.code();
+        match serde_json::from_str::<GraphQlResponse>(response.body()) {
+            Ok(out) => Ok(out),
+            Err(_) if !(200..300).contains(&code) => Err(GraphQlError::Status(code, response.body().to_string())),
+            Err(err) => Err(GraphQlError::Decode(err.to_string())),
+        }
+    }
+
+    /// Runs the query and deserializes `data` into `T`.
+    /// Any GraphQL error fails the call, even if partial data came back.
+    pub fn send<T: DeserializeOwned>(&self) -> Result<T, GraphQlError> {
+        let response = self.send_raw()?;
+        if !response.errors.is_empty() {
+            return Err(GraphQlError::GraphQl { errors: response.errors, data: response.data });
+        }
+        let data = response.data.ok_or_else(|| GraphQlError::Decode("response has no data".to_string()))?;
+        serde_json::from_value(data).map_err(|err| GraphQlError::Decode(err.to_string()))
+    }
+}
@@ -0,0 +1,40 @@
+//! Names of common HTTP headers, and checks for what may appear in a header.
+
+pub const ACCEPT: &str = "Accept";
+pub const ACCEPT_ENCODING: &str = "Accept-Encoding";
+pub const ACCEPT_LANGUAGE: &str = "Accept-Language";
+pub const AUTHORIZATION: &str = "Authorization";
+pub const CACHE_CONTROL: &str = "Cache-Control";
+pub const CONTENT_LENGTH: &str = "Content-Length";
+pub const CONTENT_TYPE: &str = "Content-Type";
+pub const COOKIE: &str = "Cookie";
+pub const ETAG: &str = "ETag";
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+pub const IF_MODIFIED_SINCE: &str = "If-Modified-Since";
+pub const IF_NONE_MATCH: &str = "If-None-Match";
+pub const LAST_MODIFIED: &str = "Last-Modified";
+pub const LINK: &str = "Link";
+pub const LOCATION: &str = "Location";
+pub const RETRY_AFTER: &str = "Retry-After";
+pub const SET_COOKIE: &str = "Set-Cookie";
+pub const USER_AGENT: &str = "User-Agent";
+pub const X_REQUEST_ID: &str = "X-Request-Id";
+
+/// Checks that a header name is a valid HTTP token.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Header name is empty".to_string());
+    }
+    match name.chars().find(|c| !(c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(*c))) {
+        Some(c) => Err(format!("Header name '{name}' contains the illegal character {c:?}")),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a header value has no line breaks or other control characters, which could inject extra headers.
+pub fn validate_value(name: &str, value: &str) -> Result<(), String> {
+    match value.chars().find(|c| c.is_control() && *c != '\t') {
+        Some(c) => Err(format!("Value of header '{name}' contains the illegal character {c:?}")),
+        None => Ok(()),
+    }
+}
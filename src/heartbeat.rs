@@ -0,0 +1,24 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::value_to_host;
+
+#[derive(Serialize)]
+struct HeartbeatIn {
+    // Whatever the step wants to show about where it is, e.g. the last record it processed.
+    details: Value,
+}
+
+/// Tells the host the running step is slow but alive, e.g. once per page of a long export.
+/// Hosts that apply liveness timeouts treat a function that stops sending heartbeats as hung.
+/// `details` is shown alongside the heartbeat; pass `&()` when there's nothing to say.
+pub fn heartbeat<T: Serialize>(details: &T) {
+    let details = serde_json::to_value(details).unwrap_or(Value::Null);
+    let (offset, size) = value_to_host(&HeartbeatIn { details });
+    unsafe { host_heartbeat(offset, size) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_heartbeat(offset: u32, size: u32);
+}
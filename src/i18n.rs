@@ -0,0 +1,104 @@
+//! Translations for prompts and messages, so the same function can talk to users in their own language.
+
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{value_from_host, vec_parts_from_host};
+
+/// The locale of the user who invoked the function, e.g. "de-CH", as reported by the host.
+pub fn locale() -> String {
+    let offset = unsafe { host_locale() };
+    let (offset, size) = vec_parts_from_host(offset);
+    value_from_host(offset, size)
+}
+
+/// Messages keyed by locale and then by message key.
+/// Lookups fall back from a regional locale to its language ("de-CH" to "de"), then to the catalog's fallback locale.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Catalog {
+    // Locale used when there is no translation for the requested one.
+    fallback: String,
+
+    messages: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Catalog {
+    pub fn new<S: Into<String>>(fallback: S) -> Self {
+        Self { fallback: fallback.into(), messages: BTreeMap::new() }
+    }
+
+    /// Adds the translation of a message into a locale.
+    pub fn with<S: Into<String>, S1: Into<String>, S2: Into<String>>(mut self, locale: S, key: S1, message: S2) -> Self {
+        self.messages.entry(locale.into()).or_default().insert(key.into(), message.into());
+        self
+    }
+
+    /// Adds every translation of a locale, e.g. loaded from a JSON file with `include_str!`.
+    pub fn with_locale<S: Into<String>>(mut self, locale: S, messages: BTreeMap<String, String>) -> Self {
+        self.messages.entry(locale.into()).or_default().extend(messages);
+        self
+    }
+
+    /// Looks up a message, or `None` when no locale in the fallback chain has it.
+    pub fn get(&self, locale: &str, key: &str) -> Option<&str> {
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+        [locale, language, &self.fallback].into_iter()
+            .find_map(|locale| self.messages.get(locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// Translates `text` if it's a message key, and leaves it alone otherwise.
+    /// `{name}` placeholders are replaced with the matching argument.
+    pub fn translate(&self, locale: &str, text: &str, args: &[(&str, &str)]) -> String {
+        let mut out = self.get(locale, text).unwrap_or(text).to_string();
+        for (name, value) in args {
+            out = out.replace(&format!("{{{name}}}"), value);
+        }
+        out
+    }
+
+    /// Translates the titles and descriptions anywhere in a JSON schema.
+    pub(crate) fn translate_schema(&self, locale: &str, schema: &mut Value) {
+        match schema {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match value {
+                        Value::String(text) if key == "title" || key == "description" => *text = self.translate(locale, text, &[]),
+                        _ => self.translate_schema(locale, value),
+                    }
+                }
+            },
+            Value::Array(items) => items.iter_mut().for_each(|item| self.translate_schema(locale, item)),
+            _ => {},
+        }
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_locale() -> u32;
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use crate::i18n::*;
+
+    #[test]
+    fn test_catalog() {
+        let catalog = Catalog::new("en")
+            .with("en", "greeting", "Hello {name}")
+            .with("en", "title", "Expense report")
+            .with("de", "greeting", "Hallo {name}");
+        assert_eq!(catalog.translate("de-CH", "greeting", &[("name", "Heidi")]), "Hallo Heidi");
+        assert_eq!(catalog.translate("fr", "greeting", &[("name", "Amélie")]), "Hello Amélie");
+        assert_eq!(catalog.translate("de", "title", &[]), "Expense report");
+        assert_eq!(catalog.translate("de", "Not a key", &[]), "Not a key");
+
+        let mut schema = json!({ "title": "title", "properties": { "title": { "type": "string", "description": "greeting" } } });
+        catalog.translate_schema("de", &mut schema);
+        assert_eq!(schema, json!({ "title": "Expense report", "properties": { "title": { "type": "string", "description": "Hallo {name}" } } }));
+    }
+}
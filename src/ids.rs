@@ -0,0 +1,64 @@
+use std::{cell::Cell, collections::hash_map::RandomState, hash::BuildHasher};
+
+use serde::Deserialize;
+
+use crate::{value_to_host, vec_parts_from_host, value_from_host};
+use crate::datetime::now_millis;
+
+thread_local! {
+    static COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Generates a random-looking ID formatted as a version 4 UUID.
+/// Guests have no source of randomness of their own, so it's derived from the clock and a counter,
+/// which keeps IDs unique within an instance and very unlikely to collide across instances.
+pub(crate) fn new_uuid() -> String {
+    let count = COUNTER.with(|counter| {
+        counter.set(counter.get().wrapping_add(1));
+        counter.get()
+    });
+    let state = RandomState::new();
+    let now = now_millis();
+    format_uuid(state.hash_one((now, count, 0u8)), state.hash_one((now, count, 1u8)))
+}
+
+/// Formats 128 bits as a version 4, variant 1 UUID, overwriting the version and variant bits.
+pub(crate) fn format_uuid(high: u64, low: u64) -> String {
+    let high = (high & 0xffff_ffff_ffff_0fff) | 0x0000_0000_0000_4000;
+    let low = (low & 0x3fff_ffff_ffff_ffff) | 0x8000_0000_0000_0000;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32, (high >> 16) & 0xffff, high & 0xffff, low >> 48, low & 0xffff_ffff_ffff,
+    )
+}
+
+#[derive(Deserialize)]
+struct HostIdempotencyKeyOut (String);
+
+/// Gets the idempotency key for `slot` from the host.
+/// Within one run of a multi-step function, the host hands out the same key for the same slot every time,
+/// including after the function was paused and resumed. Other functions get a fresh key on every call.
+pub(crate) fn persisted_key(slot: &str) -> String {
+    let (offset, size) = value_to_host(&slot);
+    let offset = unsafe { host_idempotency_key(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: HostIdempotencyKeyOut = value_from_host(offset, size);
+    out.0
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_idempotency_key(offset: u32, size: u32) -> u32;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ids::*;
+
+    #[test]
+    fn test_uuid_format() {
+        assert_eq!(format_uuid(0, 0), "00000000-0000-4000-8000-000000000000");
+        assert_eq!(format_uuid(u64::MAX, u64::MAX), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+        assert_ne!(new_uuid(), new_uuid());
+    }
+}
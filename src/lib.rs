@@ -2,26 +2,106 @@
 #![feature(try_trait_v2)]
 #![feature(panic_hooks)]
 
-use std::{time::Duration, ops::{Try, ControlFlow, FromResidual}, convert};
+use std::{time::{Duration, SystemTime, UNIX_EPOCH}, ops::{Try, ControlFlow, FromResidual}, convert, cell::Cell};
 
-use schemars::schema::RootSchema;
+use schemars::{schema::RootSchema, JsonSchema};
 use serde::{Serialize, Deserialize};
 
 mod request;
+mod client;
 mod prompt;
+mod validate;
+mod choice;
+mod schema;
+mod file;
+mod progress;
+mod heartbeat;
+mod notify;
+mod secret;
+mod pickers;
+mod slider;
+mod table;
+mod approval;
+mod cancel;
+mod query;
+mod context;
+mod retry_later;
+mod timer;
+mod metrics;
+mod cookie;
+mod compression;
+mod charset;
+mod ratelimit;
+mod cache;
+mod paginate;
+mod datetime;
+mod ids;
+mod aws;
+mod blob;
+#[cfg(feature = "xml")]
+mod xml;
+pub mod signing;
+pub mod websocket;
+mod sse;
+mod ndjson;
+pub mod graphql;
+pub mod url;
+pub mod headers;
+pub mod oauth2;
+pub mod webhook;
+pub mod i18n;
+pub mod schedule;
+pub mod workflow;
+pub mod replay;
+#[cfg(feature = "xml")]
+pub mod soap;
 
-pub use request::{HostRequestResponse, request, RequestBuilder};
-pub use prompt::{prompt, prompt_with_schema};
+pub use request::{HostRequestResponse, HostRequestType, Headers, RequestError, default_user_agent, request, request_all, request_resumable, spawn_request, PendingRequest, RequestBuilder, RetryPolicy, RedirectPolicy, ProxySetting, TlsConfig};
+pub use prompt::{prompt, prompt_all, prompt_validated, prompt_user, prompt_role, prompt_with_defaults, prompt_with_schema, confirm, show_message, Audience, PromptBuilder, PromptOutcome, PromptResponse, FieldHints, Widget};
+pub use validate::{FieldError, FieldErrors, Rule, Validator};
+pub use choice::{Choice, prompt_select, prompt_multi_select};
+pub use schema::{SchemaBuilder, Section, form_field_schema, form_sections};
+pub use file::{FileContent, FileUpload, prompt_file};
+pub use progress::progress;
+pub use heartbeat::heartbeat;
+pub use notify::notify;
+pub use secret::Secret;
+pub use pickers::{DateField, DateTimeField, DurationField};
+pub use slider::{Slider, Rating};
+pub use table::prompt_table;
+pub use approval::{ApprovalPolicy, ApprovalResult, EscalationResult, Vote, VoteDecision, ESCALATE_SIGNAL, approve_or_escalate, request_approvals};
+pub use cancel::{on_cancel, is_cancelling, finish_multistep};
+pub use query::{expose_query, run_query};
+pub use context::{context, RunContext};
+pub use timer::{start_timer, start_timer_until, cancel_timer, TimerHandle};
+pub use retry_later::{RetryLater, RetryProbe, ProbeRetryLater, ProbeNoRetryLater, request_retry};
+pub use client::Client;
+pub use cookie::{Cookie, CookieJar};
+pub use compression::ContentEncoding;
+pub use ratelimit::RateLimiter;
+pub use cache::CachedClient;
+pub use paginate::Paginator;
+pub use aws::AwsSigV4;
+pub use blob::BlobHandle;
+pub use sse::{SseEvent, SseStream};
+pub use ndjson::NdjsonStream;
 
 pub mod prelude {
     // All of these exports are needed for the #[middle_fn()] macro to work
-    pub use macros::{middle_fn, middle_multistep_fn};
+    pub use macros::{middle_fn, middle_multistep_fn, middle_query, workflow_version, request, join, select, MiddleForm};
     pub use serde_json;
     pub use serde::{Serialize, Deserialize};
     pub use schemars::JsonSchema;
-    pub use crate::{value_from_host, value_to_host, vec_parts_to_host, FnInfo, Resumable, mprint};
-    pub use crate::{HostRequestResponse, request, RequestBuilder};
-    pub use crate::{prompt, prompt_with_schema};
+    pub use crate::{value_from_host, value_to_host, vec_parts_to_host, set_current_fn, finish_multistep, FnInfo, Resumable, mprint, on_cancel, expose_query};
+    pub use crate::{RetryLater, RetryProbe, ProbeRetryLater, ProbeNoRetryLater, request_retry};
+    pub use crate::{HostRequestResponse, RequestError, request, request_all, request_resumable, spawn_request, PendingRequest, RequestBuilder, RetryPolicy, RedirectPolicy, ProxySetting, TlsConfig};
+    pub use crate::{prompt, prompt_all, prompt_validated, prompt_user, prompt_role, prompt_with_defaults, prompt_with_schema, confirm, show_message, Audience, PromptBuilder, PromptOutcome, PromptResponse, FieldHints, Widget, FieldError, Rule, Validator};
+    pub use crate::{SchemaBuilder, Section, form_field_schema, form_sections, Choice, prompt_select, prompt_multi_select, prompt_table, FileUpload, prompt_file};
+    pub use crate::{ApprovalPolicy, ApprovalResult, EscalationResult, approve_or_escalate, request_approvals};
+    pub use crate::workflow::{parallel_begin, branch, select_results, join_all, select_all, poll_until, step_token, timed_step, transaction, with_overall_deadline, set_workflow_version};
+    pub use crate::{progress, heartbeat, notify, Secret, DateField, DateTimeField, DurationField, Slider, Rating};
+    pub use crate::Client;
+    pub use crate::{Cookie, CookieJar};
 
 }
 
@@ -134,6 +214,22 @@ pub fn mprint<S: Into<String>>(input: S) {
     unsafe { host_print(offset, size) };
 }
 
+thread_local! {
+    static CURRENT_FN: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Records which exported function the host is calling. Called by the code `#[middle_fn]` generates.
+#[doc(hidden)]
+pub fn set_current_fn(name: &'static str) {
+    CURRENT_FN.with(|current| current.set(Some(name)));
+    metrics::start_call();
+}
+
+/// The name of the exported function the host is currently calling, if any.
+pub(crate) fn current_fn() -> Option<&'static str> {
+    CURRENT_FN.with(Cell::get)
+}
+
 #[derive(Serialize)]
 pub struct FnInfo {
     pub description: String,
@@ -142,16 +238,19 @@ pub struct FnInfo {
 }
 
 // A resumable 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Resumable<T> {
     Pause,
-    Ready(T)
+    Ready(T),
+    // The run was cancelled and ends without a result, after its `on_cancel` hooks ran.
+    Cancelled,
 }
 
 impl<T> FromResidual for Resumable<T> {
     fn from_residual(residual: Resumable<convert::Infallible>) -> Self {
         match residual {
             Resumable::Pause => Resumable::Pause,
+            Resumable::Cancelled => Resumable::Cancelled,
             // For some reason, the standard library doesn't have to match this branch. Why not? 
             // Maybe see... https://github.com/rust-lang/rust/issues/51085
             Resumable::Ready(_) => panic!("not reached"),
@@ -159,6 +258,22 @@ impl<T> FromResidual for Resumable<T> {
     }
 }
 
+/// Lets `?` on a `Result` return its error from a function returning `Resumable<Result<T, E>>`, converting it with `From` like a plain `?`.
+impl<T, E, F: From<E>> FromResidual<Result<convert::Infallible, E>> for Resumable<Result<T, F>> {
+    fn from_residual(residual: Result<convert::Infallible, E>) -> Self {
+        match residual {
+            Err(err) => Resumable::Ready(Err(From::from(err))),
+        }
+    }
+}
+
+/// Lets `?` on an `Option` return `None` from a function returning `Resumable<Option<T>>`.
+impl<T> FromResidual<Option<convert::Infallible>> for Resumable<Option<T>> {
+    fn from_residual(_: Option<convert::Infallible>) -> Self {
+        Resumable::Ready(None)
+    }
+}
+
 impl<T> Try for Resumable<T> {
     type Output = T;
 
@@ -171,11 +286,76 @@ impl<T> Try for Resumable<T> {
     fn branch(self) -> ControlFlow<Self::Residual, Self::Output> {
         match self {
             Resumable::Pause => ControlFlow::Break(Resumable::Pause),
+            Resumable::Cancelled => ControlFlow::Break(Resumable::Cancelled),
             Resumable::Ready(inner) => ControlFlow::Continue(inner),
         }
     }
 }
 
+impl<T> Resumable<T> {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Resumable::Ready(_))
+    }
+    pub fn is_pause(&self) -> bool {
+        matches!(self, Resumable::Pause)
+    }
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Resumable::Cancelled)
+    }
+
+    /// Transforms the output once it's ready, keeping pauses and cancellations as they are.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Resumable<U> {
+        match self {
+            Resumable::Pause => Resumable::Pause,
+            Resumable::Ready(out) => Resumable::Ready(f(out)),
+            Resumable::Cancelled => Resumable::Cancelled,
+        }
+    }
+
+    /// Runs a next step once the output is ready, e.g. `pause(delay).and_then(|_| prompt_user("ann"))`.
+    pub fn and_then<U, F: FnOnce(T) -> Resumable<U>>(self, f: F) -> Resumable<U> {
+        match self {
+            Resumable::Pause => Resumable::Pause,
+            Resumable::Ready(out) => f(out),
+            Resumable::Cancelled => Resumable::Cancelled,
+        }
+    }
+
+    /// The output, or `err` if the run paused or was cancelled.
+    pub fn ok_or<E>(self, err: E) -> Result<T, E> {
+        match self {
+            Resumable::Ready(out) => Ok(out),
+            _ => Err(err),
+        }
+    }
+
+    /// The output. Panics if the run paused or was cancelled.
+    pub fn unwrap_ready(self) -> T {
+        match self {
+            Resumable::Ready(out) => out,
+            Resumable::Pause => panic!("called `Resumable::unwrap_ready()` on a `Pause` value"),
+            Resumable::Cancelled => panic!("called `Resumable::unwrap_ready()` on a `Cancelled` value"),
+        }
+    }
+}
+
+/// `Continue` is ready, `Break` pauses.
+impl<T> From<ControlFlow<(), T>> for Resumable<T> {
+    fn from(flow: ControlFlow<(), T>) -> Self {
+        match flow {
+            ControlFlow::Continue(out) => Resumable::Ready(out),
+            ControlFlow::Break(()) => Resumable::Pause,
+        }
+    }
+}
+
+/// `Continue` with the output once it's ready, otherwise `Break` with the pause or cancellation.
+impl<T> From<Resumable<T>> for ControlFlow<Resumable<convert::Infallible>, T> {
+    fn from(resumable: Resumable<T>) -> Self {
+        resumable.branch()
+    }
+}
+
 /// Pause execution of this multi-step function.
 pub fn pause(duration: Duration) -> Resumable<()> {
     let milis = duration.as_millis();
@@ -187,9 +367,138 @@ pub fn pause(duration: Duration) -> Resumable<()> {
     } 
 }
 
+/// Pauses before retry number `attempt`, counting from 1, so retries of many runs don't line up into a thundering herd.
+/// The delay starts at `base`, doubles on every attempt up to `cap`, and then a random amount of up to half of it is shaved off.
+/// It's the delay of a `RetryPolicy` with that base, `max_delay` and jitter, so both back off the same way.
+pub fn pause_with_backoff(attempt: u32, base: Duration, cap: Duration) -> Resumable<()> {
+    let policy = RetryPolicy::new(attempt).with_backoff_base(base).with_max_delay(cap).with_jitter(0.5);
+    pause(policy.delay_for(attempt))
+}
+
+/// Pauses for `duration`, less a random fraction of it of up to `jitter`, which is clamped to 0.0..=1.0.
+/// The host provides the randomness and hands out the same numbers when the function is replayed, so replays ask for the same pause.
+pub fn pause_jittered(duration: Duration, jitter: f64) -> Resumable<()> {
+    let random = unsafe { host_random() };
+    pause(jittered(duration, jitter, random))
+}
+
+/// `base` doubled on every attempt after the first, up to `cap`.
+pub(crate) fn backoff_delay(attempt: u32, base: Duration, cap: Duration) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(cap)
+}
+
+/// `duration` less a fraction of up to `jitter` of it, picked by `random`.
+pub(crate) fn jittered(duration: Duration, jitter: f64, random: u64) -> Duration {
+    let unit = (random % 1000) as f64 / 1000.0;
+    duration.mul_f64(1.0 - jitter.clamp(0.0, 1.0) * unit)
+}
+
+/// A point in time, in milliseconds since the unix epoch.
+/// Converts from a `SystemTime`, or from a `u64` of seconds since the unix epoch.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub fn from_millis(millis: u64) -> Self {
+        Self(millis)
+    }
+    pub fn as_millis(&self) -> u64 {
+        self.0
+    }
+    pub fn as_secs(&self) -> u64 {
+        self.0 / 1000
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(secs: u64) -> Self {
+        Self(secs.saturating_mul(1000))
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    /// Times before the unix epoch become the epoch itself.
+    fn from(time: SystemTime) -> Self {
+        let millis = time.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+        Self(millis.try_into().unwrap_or(u64::MAX))
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        UNIX_EPOCH + Duration::from_millis(timestamp.0)
+    }
+}
+
+/// Pause execution of this multi-step function until a point in time, e.g. 9am on the 1st.
+/// Unlike `pause`, the time to wake up at doesn't move when the function is replayed.
+/// Times in the past resume right away.
+pub fn pause_until<T: Into<Timestamp>>(time: T) -> Resumable<()> {
+    let resume = unsafe { host_pause_until(time.into().as_millis()) };
+    match resume {
+        0 => Resumable::Pause,
+        _ => Resumable::Ready(()),
+    }
+}
+
 #[link(wasm_import_module = "middle")]
 extern {
     pub fn host_print(offset: u32, size: u32);
     pub fn host_pause(millis: u64) -> u32;
+    pub fn host_pause_until(millis: u64) -> u32;
+    pub fn host_random() -> u64;
     pub fn host_panic(offset: u32, size: u32);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use std::{ops::ControlFlow, time::Duration};
+    use crate::*;
+
+    #[test]
+    fn test_resumable() {
+        assert_eq!(Resumable::Ready(2).map(|n| n * 2).unwrap_ready(), 4);
+        assert!(Resumable::<u32>::Pause.map(|n| n * 2).is_pause());
+        assert!(Resumable::Ready(2).and_then(|_| Resumable::<u32>::Cancelled).is_cancelled());
+        assert_eq!(Resumable::<u32>::Pause.ok_or("paused"), Err("paused"));
+
+        assert!(Resumable::from(ControlFlow::<(), u32>::Break(())).is_pause());
+        assert!(Resumable::from(ControlFlow::<(), u32>::Continue(1)).is_ready());
+        let flow: ControlFlow<_, u32> = Resumable::Cancelled.into();
+        assert!(matches!(flow, ControlFlow::Break(Resumable::Cancelled)));
+    }
+
+    #[test]
+    fn test_residuals() {
+        fn parse(input: &str) -> Resumable<Result<u32, anyhow::Error>> {
+            let n: u32 = input.parse()?;
+            Resumable::Ready(Ok(n))
+        }
+        fn first_char(input: &str) -> Resumable<Option<char>> {
+            let c = input.chars().next()?;
+            Resumable::Ready(Some(c))
+        }
+        assert_eq!(parse("12").unwrap_ready().unwrap(), 12);
+        assert!(parse("twelve").unwrap_ready().is_err());
+        assert_eq!(first_char(""), Resumable::Ready(None));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let (base, cap) = (Duration::from_secs(1), Duration::from_secs(60));
+        assert_eq!(backoff_delay(1, base, cap), Duration::from_secs(1));
+        assert_eq!(backoff_delay(4, base, cap), Duration::from_secs(8));
+        assert_eq!(backoff_delay(40, base, cap), cap);
+        assert_eq!(jittered(Duration::from_secs(10), 0.5, 500), Duration::from_millis(7500));
+        assert_eq!(jittered(Duration::from_secs(10), 0.0, 999), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_resumable_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(Resumable<u32>)).unwrap();
+        let variants = schema["oneOf"].as_array().unwrap();
+        assert_eq!(variants[0]["enum"], serde_json::json!(["Pause", "Cancelled"]));
+        assert_eq!(variants[1]["properties"]["Ready"]["type"], "integer");
+        assert_eq!(Resumable::Ready(1).clone(), Resumable::Ready(1));
+    }
+}
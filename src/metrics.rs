@@ -0,0 +1,56 @@
+use std::{cell::Cell, time::Duration};
+
+use serde::Serialize;
+
+use crate::{current_fn, value_to_host};
+use crate::datetime::now_millis;
+
+#[derive(Serialize)]
+enum MetricKind {
+    // A step of a multistep function, from when it started to when it was done.
+    Step,
+    // One call of a multistep function, from when the host called it to when it paused or returned.
+    Call,
+}
+
+#[derive(Serialize)]
+struct MetricIn<'a> {
+    kind: MetricKind,
+    name: &'a str,
+    function: Option<&'static str>,
+    millis: u64,
+}
+
+thread_local! {
+    // When the host called the current function, in milliseconds since the unix epoch.
+    static CALL_STARTED: Cell<Option<u128>> = const { Cell::new(None) };
+}
+
+/// Reports how long a step took, so slow steps show up in the host's metrics.
+pub(crate) fn record_step_duration(step: &str, duration: Duration) {
+    send(MetricKind::Step, step, duration);
+}
+
+/// Notes when the host called the current function.
+pub(crate) fn start_call() {
+    CALL_STARTED.with(|started| started.set(Some(now_millis())));
+}
+
+/// Reports how long the current call of a multistep function took.
+pub(crate) fn finish_call() {
+    if let Some(started) = CALL_STARTED.with(Cell::take) {
+        let millis = now_millis().saturating_sub(started).try_into().unwrap_or(u64::MAX);
+        send(MetricKind::Call, current_fn().unwrap_or_default(), Duration::from_millis(millis));
+    }
+}
+
+fn send(kind: MetricKind, name: &str, duration: Duration) {
+    let millis = duration.as_millis().try_into().unwrap_or(u64::MAX);
+    let (offset, size) = value_to_host(&MetricIn { kind, name, function: current_fn(), millis });
+    unsafe { host_metric(offset, size) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_metric(offset: u32, size: u32);
+}
@@ -0,0 +1,134 @@
+use std::marker::PhantomData;
+
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::{HostRequestResponse, RequestBuilder, RequestError, value_to_host, vec_parts_from_host, value_from_host};
+use crate::sse::{host_sse_open, host_sse_next, host_sse_close};
+
+impl HostRequestResponse {
+    /// Deserializes a newline-delimited JSON body, one value per line. Blank lines are skipped.
+    pub fn ndjson<T: DeserializeOwned>(&self) -> serde_json::Result<Vec<T>> {
+        self.body().lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect()
+    }
+}
+
+/// Collects chunks of text and hands out the complete lines in them.
+#[derive(Default)]
+struct LineBuffer {
+    buffer: String,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+    }
+
+    /// Takes the next non-blank line out of the buffer.
+    /// Once `eof` is set, whatever is left counts as the last line, even without a newline.
+    fn next_line(&mut self, eof: bool) -> Option<String> {
+        loop {
+            let line = match self.buffer.find('\n') {
+                Some(idx) => {
+                    let line = self.buffer[..idx].to_string();
+                    self.buffer.drain(..=idx);
+                    line
+                },
+                None if eof && !self.buffer.is_empty() => std::mem::take(&mut self.buffer),
+                None => return None,
+            };
+            let line = line.trim();
+            if !line.is_empty() {
+                return Some(line.to_string());
+            }
+        }
+    }
+}
+
+/// A stream of values from a newline-delimited JSON response.
+/// The host reads the response incrementally, so exports far larger than guest memory can be worked through.
+pub struct NdjsonStream<T> {
+    handle: u64,
+    lines: LineBuffer,
+    done: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<T> NdjsonStream<T> {
+    /// Closes the stream on the host.
+    pub fn close(self) {
+        drop(self)
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for NdjsonStream<T> {
+    type Item = Result<T, RequestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.lines.next_line(self.done) {
+                return Some(serde_json::from_str(&line).map_err(|err| RequestError::Serde(err.to_string())));
+            }
+            if self.done {
+                return None;
+            }
+            let offset = unsafe { host_sse_next(self.handle) };
+            let (offset, size) = vec_parts_from_host(offset);
+            let out: NdjsonNextOut = value_from_host(offset, size);
+            match out.0 {
+                Ok(Some(chunk)) => self.lines.push(&chunk),
+                Ok(None) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                },
+            }
+        }
+    }
+}
+
+impl<T> Drop for NdjsonStream<T> {
+    fn drop(&mut self) {
+        unsafe { host_sse_close(self.handle) };
+    }
+}
+
+#[derive(Deserialize)]
+struct NdjsonOpenOut (Result<u64, RequestError>);
+
+// The next raw chunk of the body, or `None` once it has been read completely.
+#[derive(Deserialize)]
+struct NdjsonNextOut (Result<Option<String>, RequestError>);
+
+impl RequestBuilder {
+    /// Makes the request and returns an iterator over the lines of a newline-delimited JSON response.
+    /// The body is streamed the same way as a Server-Sent Events stream; each call to `next` blocks until a line is in.
+    pub fn call_ndjson<T: DeserializeOwned>(&self) -> Result<NdjsonStream<T>, RequestError> {
+        self.validate()?;
+        let input = self.prepare()?;
+        let (offset, size) = value_to_host(&input);
+        let offset = unsafe { host_sse_open(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: NdjsonOpenOut = value_from_host(offset, size);
+        out.0.map(|handle| NdjsonStream { handle, lines: LineBuffer::default(), done: false, _marker: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ndjson::*;
+
+    #[test]
+    fn test_split_lines() {
+        let mut lines = LineBuffer::default();
+        lines.push("{\"a\":1}\r\n\n{\"a\"");
+        assert_eq!(lines.next_line(false), Some("{\"a\":1}".to_string()));
+        assert_eq!(lines.next_line(false), None);
+        lines.push(":2}");
+        assert_eq!(lines.next_line(false), None);
+        assert_eq!(lines.next_line(true), Some("{\"a\":2}".to_string()));
+        assert_eq!(lines.next_line(true), None);
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+use crate::{Audience, value_to_host};
+
+#[derive(Serialize)]
+struct NotifyIn {
+    to: Audience,
+    title: String,
+    body: String,
+}
+
+/// Tells a user, role or channel that something happened, without waiting for an answer.
+/// Unlike `prompt`, this never pauses the function. Delivery is up to the host.
+pub fn notify<S: Into<String>, S1: Into<String>>(to: Audience, title: S, body: S1) {
+    let input = NotifyIn { to, title: title.into(), body: body.into() };
+    let (offset, size) = value_to_host(&input);
+    unsafe { host_notify(offset, size) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_notify(offset: u32, size: u32);
+}
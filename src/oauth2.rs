@@ -0,0 +1,181 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use serde::Deserialize;
+
+use crate::{RequestBuilder, RequestError};
+use crate::datetime::now;
+
+// Tokens this close to expiring are refreshed before use, so they don't expire in flight.
+const EXPIRY_MARGIN_SECS: u64 = 30;
+
+/// Which OAuth2 grant is used to obtain tokens.
+#[derive(Clone)]
+enum Grant {
+    ClientCredentials,
+    RefreshToken(String),
+}
+
+#[derive(Clone)]
+struct CachedToken {
+    access_token: String,
+    // Seconds since the unix epoch. Tokens without `expires_in` are kept until the server rejects them.
+    expires_at: Option<u64>,
+}
+
+struct TokenState {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    grant: Grant,
+    token: Option<CachedToken>,
+}
+
+/// The token endpoint's response, per RFC 6749 section 5.1.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// An error response from the token endpoint, per RFC 6749 section 5.2.
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Why an access token couldn't be obtained.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OAuth2Error {
+    // The token endpoint couldn't be reached.
+    Request(RequestError),
+
+    // The token endpoint refused to issue a token, e.g. "invalid_client".
+    Endpoint { error: String, description: Option<String> },
+
+    // The token endpoint returned something that isn't a token response.
+    Decode(String),
+}
+
+impl fmt::Display for OAuth2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuth2Error::Request(err) => write!(f, "OAuth2 token request failed: {err}"),
+            OAuth2Error::Endpoint { error, description: Some(description) } => write!(f, "OAuth2 token endpoint returned {error}: {description}"),
+            OAuth2Error::Endpoint { error, description: None } => write!(f, "OAuth2 token endpoint returned {error}"),
+            OAuth2Error::Decode(err) => write!(f, "Unable to decode OAuth2 token response: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for OAuth2Error {}
+
+/// Fetches access tokens from an OAuth2 token endpoint and caches them until they expire.
+/// Cloning gives another handle to the same cache, so one source can be shared by many requests.
+#[derive(Clone)]
+pub struct TokenSource(Rc<RefCell<TokenState>>);
+
+impl TokenSource {
+    /// Uses the client credentials grant, for server-to-server access.
+    pub fn client_credentials<S: Into<String>, S1: Into<String>, S2: Into<String>>(token_url: S, client_id: S1, client_secret: S2) -> Self {
+        Self::new(token_url.into(), client_id.into(), client_secret.into(), Grant::ClientCredentials)
+    }
+
+    /// Uses the refresh token grant, for acting on behalf of a user who authorized the app earlier.
+    /// If the server rotates refresh tokens, the new one is kept and used next time.
+    pub fn refresh_token<S: Into<String>, S1: Into<String>, S2: Into<String>, S3: Into<String>>(token_url: S, client_id: S1, client_secret: S2, refresh_token: S3) -> Self {
+        Self::new(token_url.into(), client_id.into(), client_secret.into(), Grant::RefreshToken(refresh_token.into()))
+    }
+
+    fn new(token_url: String, client_id: String, client_secret: String, grant: Grant) -> Self {
+        TokenSource(Rc::new(RefCell::new(TokenState {
+            token_url,
+            client_id,
+            client_secret,
+            scopes: vec![],
+            grant,
+            token: None,
+        })))
+    }
+
+    /// Requests these scopes with every token.
+    pub fn with_scopes<I, S>(self, scopes: I) -> Self where I: IntoIterator<Item = S>, S: Into<String> {
+        self.0.borrow_mut().scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns a valid access token, fetching a new one if there is none or it's about to expire.
+    pub fn access_token(&self) -> Result<String, OAuth2Error> {
+        let now = now();
+        if let Some(token) = &self.0.borrow().token {
+            if !matches!(token.expires_at, Some(expires_at) if expires_at <= now + EXPIRY_MARGIN_SECS) {
+                return Ok(token.access_token.clone());
+            }
+        }
+        self.fetch(now)
+    }
+
+    /// Forgets the cached token, e.g. after the API rejected it.
+    pub fn invalidate(&self) {
+        self.0.borrow_mut().token = None;
+    }
+
+    fn fetch(&self, now: u64) -> Result<String, OAuth2Error> {
+        let request = {
+            let state = self.0.borrow();
+            let request = RequestBuilder::post(state.token_url.as_str())
+                .set_basic_auth(state.client_id.as_str(), state.client_secret.as_str());
+            let request = match &state.grant {
+                Grant::ClientCredentials => request.set_form_key("grant_type", "client_credentials"),
+                Grant::RefreshToken(refresh_token) => request
+                    .set_form_key("grant_type", "refresh_token")
+                    .set_form_key("refresh_token", refresh_token.as_str()),
+            };
+            match state.scopes.is_empty() {
+                true => request,
+                false => request.set_form_key("scope", state.scopes.join(" ")),
+            }
+        };
+
+        let response = request.call().map_err(OAuth2Error::Request)?;
+        if !(200..300).contains(&response.code()) {
+            return Err(match serde_json::from_str::<TokenErrorResponse>(response.body()) {
+                Ok(err) => OAuth2Error::Endpoint { error: err.error, description: err.error_description },
+                Err(_) => OAuth2Error::Endpoint { error: format!("HTTP {}", response.code()), description: Some(response.body().to_string()) },
+            });
+        }
+        let token: TokenResponse = serde_json::from_str(response.body()).map_err(|err| OAuth2Error::Decode(err.to_string()))?;
+
+        let mut state = self.0.borrow_mut();
+        if let (Grant::RefreshToken(current), Some(rotated)) = (&mut state.grant, token.refresh_token) {
+            *current = rotated;
+        }
+        state.token = Some(CachedToken {
+            access_token: token.access_token.clone(),
+            expires_at: token.expires_in.map(|expires_in| now + expires_in),
+        });
+        Ok(token.access_token)
+    }
+}
+
+impl fmt::Debug for TokenSource {
+    // Never print secrets or tokens.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.0.borrow();
+        f.debug_struct("TokenSource")
+            .field("token_url", &state.token_url)
+            .field("client_id", &state.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for TokenSource {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
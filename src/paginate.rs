@@ -0,0 +1,140 @@
+use crate::{HostRequestResponse, RequestBuilder, RequestError};
+use crate::url::Url;
+use crate::headers;
+
+type CursorExtractor<'a> = Box<dyn FnMut(&HostRequestResponse) -> Option<String> + 'a>;
+
+/// How the paginator finds the next page.
+enum PageStrategy<'a> {
+    // Follow the RFC 5988 `Link: <...>; rel="next"` header.
+    LinkHeader,
+
+    // Pull a cursor out of each response and send it as a query parameter of the first request.
+    Cursor { param: String, extract: CursorExtractor<'a> },
+}
+
+/// Iterates over the pages of a paginated API, making one request per page.
+/// Iteration stops after the last page, or after the first failed request.
+pub struct Paginator<'a> {
+    first: RequestBuilder,
+    next: Option<RequestBuilder>,
+    strategy: PageStrategy<'a>,
+    max_pages: Option<usize>,
+    pages: usize,
+}
+
+impl<'a> Paginator<'a> {
+    /// Pages through an API that links to the next page with a `Link` header, like GitHub's.
+    pub fn link_header(first: RequestBuilder) -> Self {
+        Self::with_strategy(first, PageStrategy::LinkHeader)
+    }
+
+    /// Pages through a cursor-based API. `extract` returns the cursor for the next page, or `None` on the last page.
+    /// The cursor is added to the first request as the query parameter `param`.
+    pub fn cursor<S, F>(first: RequestBuilder, param: S, extract: F) -> Self
+    where S: Into<String>, F: FnMut(&HostRequestResponse) -> Option<String> + 'a {
+        Self::with_strategy(first, PageStrategy::Cursor { param: param.into(), extract: Box::new(extract) })
+    }
+
+    fn with_strategy(first: RequestBuilder, strategy: PageStrategy<'a>) -> Self {
+        Self { next: Some(first.clone()), first, strategy, max_pages: None, pages: 0 }
+    }
+
+    /// Stops after this many pages, as a safeguard against APIs that never stop linking.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Works out the request for the page after `response`.
+    fn next_request(&mut self, current: &RequestBuilder, response: &HostRequestResponse) -> Option<RequestBuilder> {
+        match &mut self.strategy {
+            PageStrategy::LinkHeader => {
+                let next = response.headers().get_all(headers::LINK)
+                    .into_iter()
+                    .find_map(next_link)?;
+                let next = resolve(current.url(), &next)?;
+                Some(current.clone().with_url(next))
+            },
+            PageStrategy::Cursor { param, extract } => {
+                let cursor = extract(response)?;
+                let url = Url::parse(self.first.url()).ok()?.with_query(param.as_str(), cursor);
+                Some(self.first.clone().with_url(url.to_string()))
+            },
+        }
+    }
+}
+
+impl Iterator for Paginator<'_> {
+    type Item = Result<HostRequestResponse, RequestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if matches!(self.max_pages, Some(max_pages) if self.pages >= max_pages) {
+            return None;
+        }
+        let current = self.next.take()?;
+        let response = match current.call() {
+            Ok(response) => response,
+            Err(err) => return Some(Err(err)),
+        };
+        self.pages += 1;
+        self.next = self.next_request(&current, &response);
+        Some(Ok(response))
+    }
+}
+
+/// Finds the `rel="next"` target in a Link header value.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        let is_next = parts.any(|param| {
+            match param.trim().split_once('=') {
+                Some((key, value)) => key.trim().eq_ignore_ascii_case("rel")
+                    && value.trim().trim_matches('"').split_whitespace().any(|rel| rel.eq_ignore_ascii_case("next")),
+                None => false,
+            }
+        });
+        is_next.then(|| target.to_string())
+    })
+}
+
+/// Resolves a possibly relative link against the URL of the page that contained it.
+fn resolve(base: &str, link: &str) -> Option<String> {
+    if Url::parse(link).is_ok() {
+        return Some(link.to_string());
+    }
+    let base = Url::parse(base).ok()?;
+    let origin = match base.port() {
+        Some(port) => format!("{}://{}:{port}", base.scheme(), base.host()),
+        None => format!("{}://{}", base.scheme(), base.host()),
+    };
+    if link.starts_with('/') {
+        return Some(format!("{origin}{link}"));
+    }
+    if link.starts_with('?') {
+        return Some(format!("{origin}{}{link}", base.path()));
+    }
+    let dir = &base.path()[..base.path().rfind('/').map(|idx| idx + 1).unwrap_or(0)];
+    Some(format!("{origin}{dir}{link}"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::paginate::*;
+
+    #[test]
+    fn test_next_link() {
+        let header = r#"<https://api.github.com/repos?page=3>; rel="next", <https://api.github.com/repos?page=50>; rel="last""#;
+        assert_eq!(next_link(header), Some("https://api.github.com/repos?page=3".to_string()));
+        assert_eq!(next_link(r#"<https://x.com/?page=1>; rel="prev""#), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("https://x.com/a/b?p=1", "/c?p=2"), Some("https://x.com/c?p=2".to_string()));
+        assert_eq!(resolve("https://x.com/a/b?p=1", "?p=2"), Some("https://x.com/a/b?p=2".to_string()));
+        assert_eq!(resolve("https://x.com/a/b", "c"), Some("https://x.com/a/c".to_string()));
+        assert_eq!(resolve("https://x.com/a", "https://y.com/"), Some("https://y.com/".to_string()));
+    }
+}
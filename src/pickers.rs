@@ -0,0 +1,418 @@
+use std::{fmt, time::Duration};
+
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::{InstanceType, Schema, SchemaObject}};
+use serde::{Serialize, Deserialize};
+
+/// A string schema with a format the host renders as a picker.
+fn picker_schema(format: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        format: Some(format.to_string()),
+        ..Default::default()
+    }.into()
+}
+
+/// A calendar date, rendered as a date picker. Travels as an ISO 8601 date, e.g. "2024-05-31".
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct DateField {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl DateField {
+    /// Returns `None` for dates that don't exist, such as February 30th.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn parse_date(date: &str) -> Option<DateField> {
+    let mut parts = date.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    DateField::new(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+}
+
+impl fmt::Display for DateField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl TryFrom<String> for DateField {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_date(&value).ok_or_else(|| format!("Invalid date {value}, expected YYYY-MM-DD"))
+    }
+}
+
+impl From<DateField> for String {
+    fn from(value: DateField) -> Self {
+        value.to_string()
+    }
+}
+
+impl JsonSchema for DateField {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        "Date".to_string()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        picker_schema("date")
+    }
+}
+
+/// A date and time with a UTC offset, rendered as a date and time picker.
+/// Travels as an RFC 3339 timestamp, e.g. "2024-05-31T17:30:00+02:00". The host fills in the user's offset.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct DateTimeField {
+    date: DateField,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    nanosecond: u32,
+
+    // Offset from UTC, in minutes east.
+    offset_minutes: i32,
+}
+
+impl DateTimeField {
+    pub fn date(&self) -> DateField {
+        self.date
+    }
+    pub fn hour(&self) -> u32 {
+        self.hour
+    }
+    pub fn minute(&self) -> u32 {
+        self.minute
+    }
+    pub fn second(&self) -> u32 {
+        self.second
+    }
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+    pub fn offset_minutes(&self) -> i32 {
+        self.offset_minutes
+    }
+}
+
+fn parse_date_time(value: &str) -> Option<DateTimeField> {
+    let (date, rest) = value.split_once(['T', 't', ' '])?;
+    let date = parse_date(date)?;
+
+    // The offset is either Z or starts with the last sign in the string.
+    let (time, offset_minutes) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let idx = rest.rfind(['+', '-'])?;
+        let (hours, minutes) = rest[idx + 1..].split_once(':')?;
+        let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        let offset = hours * 60 + minutes;
+        (&rest[..idx], if rest[idx..].starts_with('-') { -offset } else { offset })
+    };
+
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    let mut parts = time.split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+    let second: u32 = parts.next().map(|s| s.parse().ok()).unwrap_or(Some(0))?;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let nanosecond = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.len() <= 9 && fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{fraction:0<9}").parse().ok()?
+        },
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(DateTimeField { date, hour, minute, second, nanosecond, offset_minutes })
+}
+
+impl fmt::Display for DateTimeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)?;
+        if self.nanosecond > 0 {
+            write!(f, ".{}", format!("{:09}", self.nanosecond).trim_end_matches('0'))?;
+        }
+        match self.offset_minutes {
+            0 => write!(f, "Z"),
+            offset => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                write!(f, "{sign}{:02}:{:02}", offset.abs() / 60, offset.abs() % 60)
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for DateTimeField {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_date_time(&value).ok_or_else(|| format!("Invalid date and time {value}, expected an RFC 3339 timestamp"))
+    }
+}
+
+impl From<DateTimeField> for String {
+    fn from(value: DateTimeField) -> Self {
+        value.to_string()
+    }
+}
+
+impl JsonSchema for DateTimeField {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        "DateTime".to_string()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        picker_schema("date-time")
+    }
+}
+
+/// A length of time, rendered as a duration picker. Travels as an ISO 8601 duration, e.g. "P1DT2H30M".
+/// Years and months aren't accepted, as their length depends on when they start.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct DurationField(Duration);
+
+impl DurationField {
+    pub fn new(duration: Duration) -> Self {
+        Self(duration)
+    }
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    if rest.is_empty() || rest.ends_with('T') {
+        return None;
+    }
+    let (mut secs, mut nanos, mut in_time) = (0u64, 0u32, false);
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            _ => {
+                let unit = match (in_time, c) {
+                    (false, 'W') => 604800,
+                    (false, 'D') => 86400,
+                    (true, 'H') => 3600,
+                    (true, 'M') => 60,
+                    (true, 'S') => 1,
+                    _ => return None,
+                };
+                // Only seconds may have a fraction.
+                match number.split_once('.') {
+                    Some((whole, fraction)) if unit == 1 && !fraction.is_empty() && fraction.len() <= 9 => {
+                        secs = secs.checked_add(whole.parse().ok()?)?;
+                        nanos = format!("{fraction:0<9}").parse().ok()?;
+                    },
+                    Some(_) => return None,
+                    None => secs = secs.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?,
+                }
+                number.clear();
+            },
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::new(secs, nanos))
+}
+
+impl fmt::Display for DurationField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (days, hours, minutes, seconds) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        write!(f, "P")?;
+        if days > 0 {
+            write!(f, "{days}D")?;
+        }
+        if hours == 0 && minutes == 0 && seconds == 0 && self.0.subsec_nanos() == 0 {
+            return if days == 0 { write!(f, "T0S") } else { Ok(()) };
+        }
+        write!(f, "T")?;
+        if hours > 0 {
+            write!(f, "{hours}H")?;
+        }
+        if minutes > 0 {
+            write!(f, "{minutes}M")?;
+        }
+        match self.0.subsec_nanos() {
+            0 if seconds > 0 => write!(f, "{seconds}S"),
+            0 => Ok(()),
+            nanos => write!(f, "{seconds}.{}S", format!("{nanos:09}").trim_end_matches('0')),
+        }
+    }
+}
+
+impl TryFrom<String> for DurationField {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_duration(&value).map(Self).ok_or_else(|| format!("Invalid duration {value}, expected e.g. P1DT2H30M"))
+    }
+}
+
+impl From<DurationField> for String {
+    fn from(value: DurationField) -> Self {
+        value.to_string()
+    }
+}
+
+impl From<DurationField> for Duration {
+    fn from(value: DurationField) -> Self {
+        value.0
+    }
+}
+
+impl JsonSchema for DurationField {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        "Duration".to_string()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        picker_schema("duration")
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+
+    use crate::pickers::{DateField, DateTimeField, DurationField};
+
+    impl From<DateField> for NaiveDate {
+        fn from(value: DateField) -> Self {
+            NaiveDate::from_ymd_opt(value.year, value.month, value.day).expect("DateField is always a valid date")
+        }
+    }
+
+    impl From<DateTimeField> for DateTime<FixedOffset> {
+        fn from(value: DateTimeField) -> Self {
+            let offset = FixedOffset::east_opt(value.offset_minutes * 60).expect("offsets are less than a day");
+            let local = NaiveDate::from(value.date)
+                .and_hms_nano_opt(value.hour, value.minute, value.second, value.nanosecond)
+                .expect("DateTimeField is always a valid time");
+            offset.from_local_datetime(&local).single().expect("fixed offsets are never ambiguous")
+        }
+    }
+
+    impl TryFrom<DurationField> for chrono::Duration {
+        type Error = chrono::OutOfRangeError;
+
+        fn try_from(value: DurationField) -> Result<Self, Self::Error> {
+            chrono::Duration::from_std(value.0)
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+    use crate::pickers::{DateField, DateTimeField, DurationField};
+
+    impl From<DateField> for Date {
+        fn from(value: DateField) -> Self {
+            let month = Month::try_from(value.month as u8).expect("DateField is always a valid date");
+            Date::from_calendar_date(value.year, month, value.day as u8).expect("DateField is always a valid date")
+        }
+    }
+
+    impl From<DateTimeField> for OffsetDateTime {
+        fn from(value: DateTimeField) -> Self {
+            let time = Time::from_hms_nano(value.hour as u8, value.minute as u8, value.second as u8, value.nanosecond)
+                .expect("DateTimeField is always a valid time");
+            let offset = UtcOffset::from_whole_seconds(value.offset_minutes * 60).expect("offsets are less than a day");
+            PrimitiveDateTime::new(Date::from(value.date), time).assume_offset(offset)
+        }
+    }
+
+    impl TryFrom<DurationField> for time::Duration {
+        type Error = time::error::ConversionRange;
+
+        fn try_from(value: DurationField) -> Result<Self, Self::Error> {
+            time::Duration::try_from(value.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pickers::*;
+
+    #[test]
+    fn test_date() {
+        assert_eq!(parse_date("2024-02-29"), DateField::new(2024, 2, 29));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2024-2-29"), None);
+        assert_eq!(DateField::new(1969, 7, 20).unwrap().to_string(), "1969-07-20");
+    }
+
+    #[test]
+    fn test_date_time() {
+        let value = parse_date_time("2024-05-31T17:30:00.25-02:30").unwrap();
+        assert_eq!((value.hour(), value.minute(), value.nanosecond(), value.offset_minutes()), (17, 30, 250_000_000, -150));
+        assert_eq!(value.to_string(), "2024-05-31T17:30:00.25-02:30");
+        assert_eq!(parse_date_time("2024-05-31T17:30Z").unwrap().to_string(), "2024-05-31T17:30:00Z");
+        assert_eq!(parse_date_time("2024-05-31T17:30:00"), None);
+        assert_eq!(parse_date_time("2024-05-31T24:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(parse_duration("P1DT2H30M"), Some(Duration::from_secs(95400)));
+        assert_eq!(parse_duration("P2W"), Some(Duration::from_secs(1209600)));
+        assert_eq!(parse_duration("PT1.5S"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("P1M"), None);
+        assert_eq!(parse_duration("PT"), None);
+        assert_eq!(parse_duration("PT1.5M"), None);
+        assert_eq!(DurationField::new(Duration::from_secs(95400)).to_string(), "P1DT2H30M");
+        assert_eq!(DurationField::new(Duration::from_millis(1500)).to_string(), "PT1.5S");
+        assert_eq!(DurationField::new(Duration::ZERO).to_string(), "PT0S");
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+use crate::value_to_host;
+
+#[derive(Serialize)]
+struct ProgressIn {
+    // Between 0 and 100.
+    percent: f32,
+    message: String,
+}
+
+/// Reports how far along the running function is, so the host can show a progress bar instead of appearing frozen.
+/// `percent` is clamped to 0..=100. Calls are cheap, but once every few hundred records is plenty.
+pub fn progress<S: Into<String>>(percent: f32, message: S) {
+    let input = ProgressIn { percent: percent.clamp(0.0, 100.0), message: message.into() };
+    let (offset, size) = value_to_host(&input);
+    unsafe { host_progress(offset, size) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_progress(offset: u32, size: u32);
+}
@@ -1,60 +1,910 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use anyhow::Error;
-use schemars::{JsonSchema, schema::RootSchema};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::{JsonSchema, schema::{RootSchema, Schema}};
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
+use serde_path_to_error::Segment;
 
-use crate::{Resumable, value_to_host, vec_parts_from_host, value_from_host};
+use crate::{BlobHandle, Resumable, value_to_host, vec_parts_from_host, value_from_host};
+use crate::validate::{group_errors, FieldError, FieldErrors, Rule, Validator};
+use crate::i18n::{self, Catalog};
+use crate::secret::SECRET_EXTENSION;
+use crate::{replay, workflow};
 
 
 /// Prompt the user to fill out a form.
-/// The filled value, or an error, is returned.
-pub fn prompt<'a, T>() -> Resumable<Result<T, Error>> where T: JsonSchema + for<'de> Deserialize<'de> {
-    let schema = schemars::schema_for!(T);
-    let value = match prompt_with_schema(schema)? {
-        Ok(value) => value,
-        Err(err) => return Resumable::Ready(Err(err)),
-    };
+/// The filled value is returned, unless the user dismissed the form, it expired or there was an error.
+pub fn prompt<T>() -> Resumable<PromptOutcome<T>> where T: JsonSchema + for<'de> Deserialize<'de> {
+    PromptBuilder::new::<T>().prompt()
+}
 
-    // Convert the value given by the host back into the type it's supposed to be in.
-    let out: T = match serde_json::from_value(value) {
-        Ok(out) => out,
-        Err(err) => return Resumable::Ready(Err(Error::new(err).context("Deserialize error"))),
-    };
+/// Prompt the user to fill out a form, checking each submission with `validate`, e.g. against a database.
+/// Rejected submissions are shown again with the errors next to their fields, until one passes or the user gives up.
+pub fn prompt_validated<T, F>(validate: F) -> Resumable<PromptOutcome<T>> where T: JsonSchema + for<'de> Deserialize<'de>, F: Fn(&T) -> Result<(), Vec<FieldError>> {
+    PromptBuilder::new::<T>().prompt_validated(validate)
+}
+
+/// Asks a specific user to fill out a form, e.g. a manager approving a request.
+pub fn prompt_user<T, S: Into<String>>(user_id: S) -> Resumable<PromptOutcome<T>> where T: JsonSchema + for<'de> Deserialize<'de> {
+    PromptBuilder::new::<T>().audience(Audience::User(user_id.into())).prompt()
+}
+
+/// Asks anyone with a role to fill out a form, e.g. whoever is on call. The first answer wins.
+pub fn prompt_role<T, S: Into<String>>(role: S) -> Resumable<PromptOutcome<T>> where T: JsonSchema + for<'de> Deserialize<'de> {
+    PromptBuilder::new::<T>().audience(Audience::Role(role.into())).prompt()
+}
 
-    Resumable::Ready(Ok(out))
+/// Prompt the user to fill out a form that starts out filled with `defaults`.
+/// Handy for "edit this record" flows, where users should only change a few fields.
+pub fn prompt_with_defaults<T>(defaults: &T) -> Resumable<PromptOutcome<T>> where T: JsonSchema + Serialize + for<'de> Deserialize<'de> {
+    let defaults = match serde_json::to_value(defaults) {
+        Ok(defaults) => defaults,
+        Err(err) => return Resumable::Ready(PromptOutcome::Error(Error::new(err).context("Serialize error"))),
+    };
+    PromptBuilder::new::<T>().prefill(defaults).prompt()
 }
 
 /// Prompt the user to fill out a form.
 /// The form will prompt will be 
-pub fn prompt_with_schema(schema: RootSchema) -> Resumable<Result<Value, Error>> {
-    // Pass the schema to the host
-    let prompt_info = PromptIn { schema };
-    let (offset, size) = value_to_host(&prompt_info);
+pub fn prompt_with_schema(schema: RootSchema) -> Resumable<PromptOutcome<Value>> {
+    PromptBuilder::with_schema(schema).prompt_value()
+}
+
+/// Asks the user a yes or no question, e.g. "Delete 120 records?".
+pub fn confirm<S: Into<String>>(message: S) -> Resumable<PromptOutcome<bool>> {
+    let schema = serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "required": ["confirmed"],
+        "properties": { "confirmed": { "type": "boolean" } },
+    });
+    let schema = serde_json::from_value(schema).expect("confirm: invalid schema");
+    PromptBuilder::with_schema(schema)
+        .with_kind(PromptKind::Confirm)
+        .title(message)
+        .prompt_until_valid(|value| match value.get("confirmed") {
+            Some(Value::Bool(confirmed)) => Ok(*confirmed),
+            _ => Err(FieldErrors::from([("confirmed".to_string(), vec!["Answer yes or no".to_string()])])),
+        })
+}
 
-    // Call prompt
-    let offset = unsafe { host_prompt(offset, size) };
+/// Shows the user a message, formatted as markdown, and waits for them to acknowledge it.
+/// For informational steps that need no data entry.
+pub fn show_message<S: Into<String>>(markdown: S) -> Resumable<()> {
+    let input = ShowMessageIn { markdown: markdown.into() };
+    let (offset, size) = value_to_host(&input);
+    let offset = unsafe { host_show_message(offset, size) };
     let (offset, size) = vec_parts_from_host(offset);
-    let out: PromptOut = value_from_host(offset, size);
+    let out: ShowMessageOut = value_from_host(offset, size);
+    out.0
+}
 
-    // Escape if we need to pause. Escape if somehow there was an error.
-    let value = match out.0? {
-        Ok(value) => value,
-        Err(err_str) => return Resumable::Ready(Err(Error::msg(err_str))),
-    };
+#[derive(Serialize)]
+struct ShowMessageIn {
+    markdown: String,
+}
+
+#[derive(Deserialize)]
+struct ShowMessageOut (Resumable<()>);
 
-    // All done!
-    Resumable::Ready(Ok(value))
+/// Who a prompt is for.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Audience {
+    // A single user, by their id on the host.
+    User(String),
+    // Anyone with the named role.
+    Role(String),
+    // Anyone in a channel, by its id on the host.
+    Channel(String),
 }
 
-#[derive(Serialize)]
-struct PromptIn {
+// Read-only content shown between the description and the fields.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub(crate) enum ContentBlock {
+    Markdown(String),
+    // A read-only file, e.g. an image preview or a generated PDF. Inline contents are base64 encoded, like uploads.
+    Attachment { filename: String, content_type: Option<String>, data: Option<String>, blob: Option<BlobHandle> },
+    // Rows of data shown as a read-only table, with a column per key.
+    Table(Vec<Value>),
+}
+
+// What the host shows.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub(crate) enum PromptKind {
+    // A form built from the schema.
+    Form,
+    // A dialog with yes and no buttons. The host answers with `{ "confirmed": bool }`.
+    Confirm,
+    // A file upload. The host answers with `{ "file": { filename, content_type, size, data or blob } }`.
+    File { accepted_types: Vec<String>, max_size: u64 },
+    // Approve and reject buttons with a comment box. The host answers with `{ "approved": bool, "comment": string }`.
+    Approval,
+}
+
+/// How the host should render a field.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Widget {
+    Text,
+    TextArea,
+    Password,
+    Number,
+    Checkbox,
+    Select,
+    MultiSelect,
+    Table,
+    Radio,
+    Date,
+    DateTime,
+    Hidden,
+    // A bar with a handle, for numbers with a minimum and a maximum.
+    Slider,
+    // A row of stars, for numbers from 1 to the field's maximum.
+    Rating,
+}
+
+/// Rendering hints for a single field. The host may ignore hints it can't honor.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct FieldHints {
+    // Widget to render instead of the one the host picks from the field's type.
+    widget: Option<Widget>,
+
+    // Example text shown in an empty field.
+    placeholder: Option<String>,
+
+    // Longer explanation shown next to the field.
+    help_text: Option<String>,
+
+    // Mask the input and keep the value out of the host's logs.
+    secret: bool,
+}
+
+impl FieldHints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn widget(mut self, widget: Widget) -> Self {
+        self.widget = Some(widget);
+        self
+    }
+    pub fn placeholder<S: Into<String>>(mut self, placeholder: S) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+    pub fn help_text<S: Into<String>>(mut self, help_text: S) -> Self {
+        self.help_text = Some(help_text.into());
+        self
+    }
+    /// Masks the input and keeps the value out of the host's logs. Fields of type `Secret` are secret already.
+    pub fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+}
+
+/// A form shown to the user, with everything the host needs to render it nicely.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct PromptBuilder {
+    kind: PromptKind,
+
+    // Shape of the value the form produces.
     schema: RootSchema,
+
+    // Heading of the form.
+    title: Option<String>,
+
+    // Plain text shown above the fields.
+    description: Option<String>,
+
+    // Richer content shown after the description, in order.
+    blocks: Vec<ContentBlock>,
+
+    // Label of the submit button, e.g. "Approve".
+    submit_label: Option<String>,
+
+    // Rendering hints, keyed by field name. Nested fields use a slash-separated path, e.g. "address/city".
+    fields: BTreeMap<String, FieldHints>,
+
+    // Values the fields start out with.
+    prefill: Option<Value>,
+
+    // Rules checked against what the user submits. The host may also check them while the user types.
+    rules: Validator,
+
+    // Errors in the previous submission, shown next to the fields they belong to.
+    errors: FieldErrors,
+
+    // How many times the form has been shown again because of errors.
+    attempt: u32,
+
+    // Who must answer. Without one, the prompt goes to the user running the function.
+    audience: Option<Audience>,
+
+    // Milliseconds the user has to answer, counted from when the host first shows the prompt.
+    ttl_millis: Option<u64>,
+
+    // Seconds since the unix epoch after which the prompt can no longer be answered.
+    deadline: Option<u64>,
+
+    // Key the host saves what the user has typed so far under, so it survives a restart of the workflow.
+    draft_key: Option<String>,
+}
+
+/// How a prompt ended.
+#[derive(Debug)]
+pub enum PromptOutcome<T> {
+    // The user submitted the form.
+    Answered(T),
+    // The user dismissed the form without submitting it.
+    Cancelled,
+    // Nobody answered before the prompt's ttl or deadline passed.
+    Expired,
+    // The prompt couldn't be shown, or its answer couldn't be read.
+    Error(Error),
+}
+
+impl<T> PromptOutcome<T> {
+    /// The submitted value, if the user answered.
+    pub fn answered(self) -> Option<T> {
+        match self {
+            PromptOutcome::Answered(out) => Some(out),
+            _ => None,
+        }
+    }
+    pub fn is_answered(&self) -> bool {
+        matches!(self, PromptOutcome::Answered(_))
+    }
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, PromptOutcome::Cancelled)
+    }
+    pub fn is_expired(&self) -> bool {
+        matches!(self, PromptOutcome::Expired)
+    }
+    /// Converts the submitted value, keeping any other outcome as it is.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> PromptOutcome<U> {
+        match self {
+            PromptOutcome::Answered(out) => PromptOutcome::Answered(f(out)),
+            PromptOutcome::Cancelled => PromptOutcome::Cancelled,
+            PromptOutcome::Expired => PromptOutcome::Expired,
+            PromptOutcome::Error(err) => PromptOutcome::Error(err),
+        }
+    }
+    /// Treats every outcome but an answer as an error, for steps that can't go on without one.
+    pub fn into_result(self) -> Result<T, Error> {
+        match self {
+            PromptOutcome::Answered(out) => Ok(out),
+            PromptOutcome::Cancelled => Err(Error::msg("The prompt was cancelled")),
+            PromptOutcome::Expired => Err(Error::msg("The prompt expired before anyone answered")),
+            PromptOutcome::Error(err) => Err(err),
+        }
+    }
+}
+
+/// A prompt's answer along with who gave it and when, for workflows that keep an audit trail.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct PromptResponse<T> {
+    value: T,
+
+    // Id on the host of the user who answered.
+    responder: String,
+
+    // Seconds since the unix epoch when the answer was submitted.
+    answered_at: u64,
+}
+
+impl<T> PromptResponse<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+    pub fn responder(&self) -> &str {
+        &self.responder
+    }
+    pub fn answered_at(&self) -> u64 {
+        self.answered_at
+    }
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+// What the host sends back once a prompt ends. The submission is MessagePack within the reply,
+// like state and event payloads, so it's decoded straight into the type asked for.
+#[derive(Deserialize)]
+pub(crate) enum PromptReply {
+    Answered { value: Submission, responder: String, answered_at: u64 },
+    Cancelled,
+    Expired,
+}
+
+/// A submitted form as the host encoded it, in MessagePack.
+/// It's decoded straight into the type the function asks for, rather than through a `Value`.
+#[derive(Deserialize)]
+pub(crate) struct Submission(#[serde(with = "serde_bytes")] Vec<u8>);
+
+impl Submission {
+    /// Decodes the submission into a `T`, reporting a failure against the field it happened in.
+    pub(crate) fn parse<T>(&self) -> Result<T, FieldErrors> where T: for<'de> Deserialize<'de> {
+        let mut deserializer = rmp_serde::Deserializer::new(&self.0[..]);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(path_errors)
+    }
+    /// Decodes the submission as JSON, which is what rules are checked against and prefills are made of.
+    pub(crate) fn value(&self) -> Result<Value, Error> {
+        rmp_serde::from_slice(&self.0).map_err(|err| Error::msg(format!("The submission couldn't be read: {err}")))
+    }
+}
+
+impl PromptBuilder {
+    /// Creates a form for filling out a `T`.
+    pub fn new<T: JsonSchema>() -> Self {
+        Self::with_schema(schemars::schema_for!(T))
+    }
+    /// Creates a form from a schema built by hand.
+    pub fn with_schema(schema: RootSchema) -> Self {
+        Self {
+            kind: PromptKind::Form,
+            schema,
+            title: None,
+            description: None,
+            blocks: vec![],
+            submit_label: None,
+            fields: BTreeMap::new(),
+            prefill: None,
+            rules: Validator::new(),
+            errors: FieldErrors::new(),
+            attempt: 0,
+            audience: None,
+            ttl_millis: None,
+            deadline: None,
+            draft_key: None,
+        }
+    }
+    pub(crate) fn with_kind(mut self, kind: PromptKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+    /// Adds a block of markdown, e.g. a summary of what's being approved, shown above the fields.
+    pub fn markdown<S: Into<String>>(mut self, markdown: S) -> Self {
+        self.blocks.push(ContentBlock::Markdown(markdown.into()));
+        self
+    }
+    /// Attaches a file for the user to look at, e.g. the invoice being approved.
+    /// Keep inline attachments small; larger files belong in the blob store, see `attach_blob`.
+    pub fn attach_bytes<S: Into<String>, S1: Into<String>>(mut self, filename: S, content_type: S1, bytes: &[u8]) -> Self {
+        self.blocks.push(ContentBlock::Attachment {
+            filename: filename.into(),
+            content_type: Some(content_type.into()),
+            data: Some(STANDARD.encode(bytes)),
+            blob: None,
+        });
+        self
+    }
+    /// Attaches a file from the host's blob store, e.g. a report downloaded with `download_to_blob`.
+    pub fn attach_blob<S: Into<String>>(mut self, filename: S, blob: &BlobHandle) -> Self {
+        self.blocks.push(ContentBlock::Attachment {
+            filename: filename.into(),
+            content_type: blob.content_type().map(str::to_string),
+            data: None,
+            blob: Some(blob.clone()),
+        });
+        self
+    }
+    /// Shows rows of data as a read-only table, with a column per field of `T`.
+    /// Rows that don't serialize are left out.
+    pub fn data_table<T: Serialize>(mut self, rows: &[T]) -> Self {
+        let rows = rows.iter().filter_map(|row| serde_json::to_value(row).ok()).collect();
+        self.blocks.push(ContentBlock::Table(rows));
+        self
+    }
+    pub fn submit_label<S: Into<String>>(mut self, submit_label: S) -> Self {
+        self.submit_label = Some(submit_label.into());
+        self
+    }
+    /// Sets the rendering hints of a field, replacing any set before.
+    pub fn field<S: Into<String>>(mut self, name: S, hints: FieldHints) -> Self {
+        self.fields.insert(name.into(), hints);
+        self
+    }
+    /// Shorthand for setting only the widget of a field.
+    pub fn widget<S: Into<String>>(mut self, name: S, widget: Widget) -> Self {
+        let hints = self.fields.entry(name.into()).or_default();
+        hints.widget = Some(widget);
+        self
+    }
+    /// Shorthand for setting only the placeholder of a field.
+    pub fn placeholder<S: Into<String>, S1: Into<String>>(mut self, name: S, placeholder: S1) -> Self {
+        let hints = self.fields.entry(name.into()).or_default();
+        hints.placeholder = Some(placeholder.into());
+        self
+    }
+    /// Shorthand for setting only the help text of a field.
+    pub fn help_text<S: Into<String>, S1: Into<String>>(mut self, name: S, help_text: S1) -> Self {
+        let hints = self.fields.entry(name.into()).or_default();
+        hints.help_text = Some(help_text.into());
+        self
+    }
+
+    /// Shorthand for marking a field as secret, e.g. an API key in a hand-built schema.
+    pub fn secret<S: Into<String>>(mut self, name: S) -> Self {
+        let hints = self.fields.entry(name.into()).or_default();
+        hints.secret = true;
+        self
+    }
+
+    /// Fills the form with values the user can change, e.g. the current state of a record being edited.
+    /// Fields missing from `values` start out empty.
+    pub fn prefill(mut self, values: Value) -> Self {
+        self.prefill = Some(values);
+        self
+    }
+
+    /// Directs the prompt to a user, role or channel instead of the user running the function.
+    pub fn audience(mut self, audience: Audience) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// Gives up on the prompt when nobody answers within `ttl`, e.g. to escalate or take a default path.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_millis = Some(ttl.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+    /// Gives up on the prompt when nobody answers before `deadline`, in seconds since the unix epoch.
+    pub fn deadline(mut self, deadline: u64) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Has the host keep saving what the user types into the form under `key`, which must be unique within the run.
+    /// Secret fields aren't saved. See `restore_draft` for picking a draft back up.
+    pub fn save_drafts<S: Into<String>>(mut self, key: S) -> Self {
+        self.draft_key = Some(key.into());
+        self
+    }
+    /// What the user had typed into the form when it was last open, if drafts are saved and there is one.
+    /// The host drops a draft once its prompt is answered.
+    pub fn draft(&self) -> Option<Value> {
+        let key = self.draft_key.as_ref()?;
+        let (offset, size) = value_to_host(&DraftIn { key });
+        let offset = unsafe { host_prompt_draft(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: DraftOut = value_from_host(offset, size);
+        out.0
+    }
+    /// Prefills the form with its saved draft, if there is one, e.g. when the form is shown again after a restart.
+    /// Fields of the draft replace those of an earlier prefill.
+    pub fn restore_draft(mut self) -> Self {
+        if let Some(draft) = self.draft() {
+            self.prefill = Some(merge_prefill(self.prefill.take(), draft));
+        }
+        self
+    }
+
+    /// Adds a validation rule for a field, on top of any constraints in the schema.
+    pub fn rule<S: Into<String>>(mut self, name: S, rule: Rule) -> Self {
+        self.rules.add(name, rule);
+        self
+    }
+    /// Adds all the rules of a validator.
+    pub fn validator(mut self, validator: &Validator) -> Self {
+        self.rules.extend(validator);
+        self
+    }
+
+    /// Translates the texts of the form, and the titles and descriptions in its schema, into the invoking user's locale.
+    /// Texts that aren't keys of the catalog are left alone.
+    pub fn localize(self, catalog: &Catalog) -> Self {
+        self.localize_to(catalog, &i18n::locale())
+    }
+    /// Like `localize`, for a locale other than the invoking user's, e.g. that of the audience.
+    pub fn localize_to(mut self, catalog: &Catalog, locale: &str) -> Self {
+        let translate = |text: &mut String| *text = catalog.translate(locale, text, &[]);
+        for text in [&mut self.title, &mut self.description, &mut self.submit_label].into_iter().flatten() {
+            translate(text);
+        }
+        for block in self.blocks.iter_mut() {
+            if let ContentBlock::Markdown(markdown) = block {
+                translate(markdown);
+            }
+        }
+        for hints in self.fields.values_mut() {
+            for text in [&mut hints.placeholder, &mut hints.help_text].into_iter().flatten() {
+                translate(text);
+            }
+        }
+        if let Ok(mut schema) = serde_json::to_value(&self.schema) {
+            catalog.translate_schema(locale, &mut schema);
+            if let Ok(schema) = serde_json::from_value(schema) {
+                self.schema = schema;
+            }
+        }
+        self
+    }
+
+    /// What the host would render for the form, as JSON: its schema, hints, content blocks and prefill.
+    /// Its rules include those implied by the schema, as every one of them is checked on submission.
+    /// Shows nothing and doesn't pause, so forms can be snapshot tested or shown by tooling.
+    pub fn preview(&self) -> Value {
+        let mut form = self.clone();
+        form.rules = self.all_rules();
+        serde_json::to_value(&form).expect("preview: form doesn't serialize")
+    }
+
+    /// Shows the form and deserializes what the user filled in.
+    /// Submissions that break a rule, or don't deserialize, are sent back to the user along with error messages.
+    pub fn prompt<T>(&self) -> Resumable<PromptOutcome<T>> where T: for<'de> Deserialize<'de> {
+        let outcome = self.respond_until_valid(|submission| submission.parse())?;
+        Resumable::Ready(outcome.map(PromptResponse::into_value))
+    }
+
+    /// Shows the form and returns what the user filled in as JSON.
+    /// Submissions that break a rule are sent back to the user along with error messages.
+    pub fn prompt_value(&self) -> Resumable<PromptOutcome<Value>> {
+        self.prompt_until_valid(Ok)
+    }
+
+    /// Like `prompt`, also checking each submission with `validate`.
+    /// Rejected submissions are sent back to the user along with the errors `validate` returned.
+    pub fn prompt_validated<T, F>(&self, validate: F) -> Resumable<PromptOutcome<T>> where T: for<'de> Deserialize<'de>, F: Fn(&T) -> Result<(), Vec<FieldError>> {
+        let outcome = self.respond_until_valid(|submission| {
+            let out = submission.parse()?;
+            validate(&out).map_err(group_errors)?;
+            Ok(out)
+        })?;
+        Resumable::Ready(outcome.map(PromptResponse::into_value))
+    }
+
+    /// Like `prompt`, also returning who answered and when.
+    pub fn prompt_response<T>(&self) -> Resumable<PromptOutcome<PromptResponse<T>>> where T: for<'de> Deserialize<'de> {
+        self.respond_until_valid(|submission| submission.parse())
+    }
+
+    /// Shows the form until the submission passes every rule and `parse` accepts it, or the prompt expires.
+    /// `parse` works on the submission as JSON, for forms whose answer is reshaped before it's returned.
+    pub(crate) fn prompt_until_valid<T, F>(&self, parse: F) -> Resumable<PromptOutcome<T>> where F: Fn(Value) -> Result<T, FieldErrors> {
+        let outcome = self.respond_until_valid(|submission| submission.parse().and_then(&parse))?;
+        Resumable::Ready(outcome.map(PromptResponse::into_value))
+    }
+
+    /// `parse` decodes the submission as sent. It's only decoded as JSON when there are rules to check or the form is shown again,
+    /// and a submission that can't be decoded as JSON then ends the prompt with an error.
+    fn respond_until_valid<T, F>(&self, parse: F) -> Resumable<PromptOutcome<PromptResponse<T>>> where F: Fn(&Submission) -> Result<T, FieldErrors> {
+        let validator = self.all_rules();
+        if let Some(err) = validator.error() {
+            return Resumable::Ready(PromptOutcome::Error(Error::msg(err.to_string())));
+        }
+        let mut form = self.clone();
+        loop {
+            let (submission, responder, answered_at) = match form.send()? {
+                Ok(PromptReply::Answered { value, responder, answered_at }) => (value, responder, answered_at),
+                Ok(PromptReply::Cancelled) => return Resumable::Ready(PromptOutcome::Cancelled),
+                Ok(PromptReply::Expired) => return Resumable::Ready(PromptOutcome::Expired),
+                Err(err) => return Resumable::Ready(PromptOutcome::Error(err)),
+            };
+            let mut value = None;
+            let mut errors = FieldErrors::new();
+            if !validator.is_empty() {
+                let checked = match submission.value() {
+                    Ok(checked) => checked,
+                    Err(err) => return Resumable::Ready(PromptOutcome::Error(err)),
+                };
+                errors = validator.check(&checked);
+                value = Some(checked);
+            }
+            if errors.is_empty() {
+                match parse(&submission) {
+                    Ok(value) => return Resumable::Ready(PromptOutcome::Answered(PromptResponse { value, responder, answered_at })),
+                    Err(parse_errors) => errors = parse_errors,
+                }
+            }
+            let value = match value.map_or_else(|| submission.value(), Ok) {
+                Ok(value) => value,
+                Err(err) => return Resumable::Ready(PromptOutcome::Error(err)),
+            };
+            // Each attempt is a separate prompt to the host, so replaying a resumed function walks through them in order.
+            form.retry(errors, value);
+        }
+    }
+
+    /// The schema's constraints along with the rules added to the builder.
+    fn all_rules(&self) -> Validator {
+        let mut validator = Validator::from_schema(&self.schema);
+        validator.extend(&self.rules);
+        validator
+    }
+
+    /// Prepares the form to be shown again with errors in a submission.
+    /// What the user submitted is kept, so they only need to fix the fields with errors. Secrets aren't sent back.
+    fn retry(&mut self, errors: FieldErrors, mut value: Value) {
+        for name in self.secret_fields() {
+            remove_field(&mut value, &name);
+        }
+        self.errors = errors;
+        self.prefill = Some(value);
+        self.attempt += 1;
+    }
+
+    /// Paths of the fields marked secret, either with a hint or by the schema of a `Secret` field.
+    fn secret_fields(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.fields.iter()
+            .filter(|(_, hints)| hints.secret)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if let Some(object) = &self.schema.schema.object {
+            for (name, property) in &object.properties {
+                if let Schema::Object(property) = property {
+                    if property.extensions.get(SECRET_EXTENSION) == Some(&Value::Bool(true)) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// The form with its deadline moved up to the overall deadline of `workflow::with_overall_deadline`, if that's sooner.
+    fn within_overall_deadline(&self) -> PromptBuilder {
+        let mut form = self.clone();
+        if let Some(overall) = workflow::overall_deadline() {
+            form.deadline = Some(form.deadline.map_or(overall.as_secs(), |deadline| deadline.min(overall.as_secs())));
+        }
+        form
+    }
+
+    /// Shows the form once.
+    fn send(&self) -> Resumable<Result<PromptReply, Error>> {
+        // Pass the form to the host and call prompt, unless the reply is replayed from the recording.
+        let out: PromptOut = replay::host_call("prompt", || {
+            let (offset, size) = value_to_host(&self.within_overall_deadline());
+            unsafe { host_prompt(offset, size) }
+        }, |out: &PromptOut| out.0.is_ready());
+
+        // Escape if we need to pause. Escape if somehow there was an error.
+        let reply = match out.0? {
+            Ok(reply) => reply,
+            Err(err_str) => return Resumable::Ready(Err(Error::msg(err_str))),
+        };
+
+        // All done!
+        Resumable::Ready(Ok(reply))
+    }
+}
+
+// Prompts the host shows at the same time.
+#[derive(Serialize)]
+struct PromptGroupIn<'a> {
+    prompts: &'a [PromptBuilder],
+
+    // Resume once at least this many of the prompts have ended.
+    min_replies: u32,
+}
+
+// The reply to each prompt of a group, or `None` while it's still open.
+#[derive(Deserialize)]
+struct PromptGroupOut (Resumable<Result<Vec<Option<PromptReply>>, String>>);
+
+/// Shows several prompts at once, possibly to different people, and pauses until every one of them has ended.
+/// A prompt's deadline or ttl ends it, so set them to avoid waiting on someone forever.
+/// Submissions that break a rule are sent back along with error messages, together with any others that need fixing.
+/// The outcomes are in the order of `prompts`.
+pub fn prompt_all(prompts: Vec<PromptBuilder>) -> Resumable<Vec<PromptOutcome<Value>>> {
+    let validators: Vec<Validator> = prompts.iter().map(PromptBuilder::all_rules).collect();
+    let mut forms = prompts;
+    // Prompts with rules that can't be checked fail without being shown.
+    let mut outcomes: Vec<Option<PromptOutcome<Value>>> = validators.iter()
+        .map(|validator| validator.error().map(|err| PromptOutcome::Error(Error::msg(err.to_string()))))
+        .collect();
+    loop {
+        let open: Vec<usize> = (0..forms.len()).filter(|i| outcomes[*i].is_none()).collect();
+        if open.is_empty() {
+            return Resumable::Ready(outcomes.into_iter().flatten().collect());
+        }
+        let group: Vec<PromptBuilder> = open.iter().map(|i| forms[*i].clone()).collect();
+        let replies = match send_group(&group, group.len())? {
+            Ok(replies) => replies,
+            Err(err) => {
+                for i in open {
+                    outcomes[i] = Some(PromptOutcome::Error(Error::msg(err.to_string())));
+                }
+                continue;
+            },
+        };
+        for (i, reply) in open.into_iter().zip(replies) {
+            outcomes[i] = match reply {
+                Some(PromptReply::Answered { value, .. }) => {
+                    let value = match value.value() {
+                        Ok(value) => value,
+                        Err(err) => {
+                            outcomes[i] = Some(PromptOutcome::Error(err));
+                            continue;
+                        },
+                    };
+                    let errors = validators[i].check(&value);
+                    if errors.is_empty() {
+                        Some(PromptOutcome::Answered(value))
+                    } else {
+                        forms[i].retry(errors, value);
+                        None
+                    }
+                },
+                Some(PromptReply::Cancelled) => Some(PromptOutcome::Cancelled),
+                Some(PromptReply::Expired) => Some(PromptOutcome::Expired),
+                None => None,
+            };
+        }
+    }
+}
+
+/// Shows several prompts at once, possibly to different people, and pauses until at least `min_replies` of them have ended.
+/// The host identifies a group by its prompts, so showing the same group again returns the replies so far.
+pub(crate) fn send_group(prompts: &[PromptBuilder], min_replies: usize) -> Resumable<Result<Vec<Option<PromptReply>>, Error>> {
+    let prompts: Vec<PromptBuilder> = prompts.iter().map(PromptBuilder::within_overall_deadline).collect();
+    let input = PromptGroupIn { prompts: &prompts, min_replies: min_replies.try_into().unwrap_or(u32::MAX) };
+    let out: PromptGroupOut = replay::host_call("prompt_group", || {
+        let (offset, size) = value_to_host(&input);
+        unsafe { host_prompt_group(offset, size) }
+    }, |out: &PromptGroupOut| out.0.is_ready());
+    match out.0? {
+        Ok(replies) => Resumable::Ready(Ok(replies)),
+        Err(err_str) => Resumable::Ready(Err(Error::msg(err_str))),
+    }
+}
+
+/// Takes down the prompts of a group that are still open, e.g. once enough approvers have answered.
+pub(crate) fn withdraw_group(prompts: &[PromptBuilder]) {
+    // The host identifies the group by its prompts as they were sent.
+    let prompts: Vec<PromptBuilder> = prompts.iter().map(PromptBuilder::within_overall_deadline).collect();
+    let input = PromptGroupIn { prompts: &prompts, min_replies: 0 };
+    let (offset, size) = value_to_host(&input);
+    unsafe { host_prompt_withdraw(offset, size) };
+}
+
+/// Lays a draft over a prefill, keeping prefilled fields the draft doesn't have.
+fn merge_prefill(prefill: Option<Value>, draft: Value) -> Value {
+    match (prefill, draft) {
+        (Some(Value::Object(mut prefill)), Value::Object(draft)) => {
+            prefill.extend(draft);
+            Value::Object(prefill)
+        },
+        (_, draft) => draft,
+    }
+}
+
+#[derive(Serialize)]
+struct DraftIn<'a> {
+    key: &'a str,
 }
 
 #[derive(Deserialize)]
-struct PromptOut (Resumable<Result<Value, String>>);
+struct DraftOut (Option<Value>);
+
+/// Removes a field from a form by its slash-separated path.
+fn remove_field(form: &mut Value, path: &str) {
+    let (parent, name) = match path.rsplit_once('/') {
+        Some((parent, name)) => (form.pointer_mut(&format!("/{parent}")), name),
+        None => (Some(form), path),
+    };
+    if let Some(Value::Object(parent)) = parent {
+        parent.remove(name);
+    }
+}
+
+/// Converts part of a submission back into the type it's supposed to be in.
+pub(crate) fn parse_value<T>(value: Value) -> Result<T, FieldErrors> where T: for<'de> Deserialize<'de> {
+    serde_path_to_error::deserialize(value).map_err(path_errors)
+}
+
+/// Files a deserialization error under the slash-separated path of the field it happened in.
+fn path_errors<E: std::fmt::Display>(err: serde_path_to_error::Error<E>) -> FieldErrors {
+    let path: Vec<String> = err.path().iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.clone()),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .collect();
+    FieldErrors::from([(path.join("/"), vec![err.into_inner().to_string()])])
+}
+
+#[derive(Deserialize)]
+struct PromptOut (Resumable<Result<PromptReply, String>>);
 
 #[link(wasm_import_module = "middle")]
 extern {
     pub fn host_prompt(offset: u32, size: u32) -> u32;
+    pub fn host_show_message(offset: u32, size: u32) -> u32;
+    pub fn host_prompt_group(offset: u32, size: u32) -> u32;
+    pub fn host_prompt_withdraw(offset: u32, size: u32);
+    pub fn host_prompt_draft(offset: u32, size: u32) -> u32;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prompt::*;
+
+    #[test]
+    fn test_outcome() {
+        assert_eq!(PromptOutcome::Answered(2).map(|n| n * 2).answered(), Some(4));
+        assert!(PromptOutcome::<u32>::Cancelled.map(|n| n * 2).is_cancelled());
+        assert!(PromptOutcome::<u32>::Expired.into_result().is_err());
+        assert_eq!(PromptOutcome::Answered("yes").into_result().unwrap(), "yes");
+    }
+
+    #[test]
+    fn test_merge_prefill() {
+        let prefill = serde_json::json!({ "name": "Ann", "team": "Ops" });
+        let draft = serde_json::json!({ "team": "Sales", "notes": "half" });
+        assert_eq!(merge_prefill(Some(prefill), draft.clone()), serde_json::json!({ "name": "Ann", "team": "Sales", "notes": "half" }));
+        assert_eq!(merge_prefill(None, draft.clone()), draft);
+    }
+
+    #[test]
+    fn test_remove_field() {
+        let mut form = serde_json::json!({ "user": "ann", "password": "x", "account": { "token": "y", "id": 1 } });
+        remove_field(&mut form, "password");
+        remove_field(&mut form, "account/token");
+        remove_field(&mut form, "missing/token");
+        assert_eq!(form, serde_json::json!({ "user": "ann", "account": { "id": 1 } }));
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Order {
+        items: Vec<Item>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Item {
+        sku: String,
+        qty: u32,
+    }
+
+    #[test]
+    fn test_submission() {
+        let form = serde_json::json!({ "items": [{ "sku": "A1", "qty": 2 }, { "sku": "B2", "qty": "many" }] });
+        let submission = Submission(rmp_serde::to_vec(&form).unwrap());
+        assert_eq!(submission.value().unwrap(), form);
+        let errors = submission.parse::<Order>().unwrap_err();
+        assert_eq!(errors.keys().collect::<Vec<_>>(), ["items/1/qty"]);
+
+        let errors = parse_value::<Item>(serde_json::json!({ "qty": 1 })).unwrap_err();
+        assert_eq!(errors[""], vec!["missing field `sku`"]);
+    }
+
+    #[test]
+    fn test_preview() {
+        let schema = crate::SchemaBuilder::new().text("name").required().build();
+        let preview = PromptBuilder::with_schema(schema)
+            .title("Who are you?")
+            .placeholder("name", "Ann")
+            .preview();
+        assert_eq!(preview["kind"], "Form");
+        assert_eq!(preview["title"], "Who are you?");
+        assert_eq!(preview["schema"]["required"], serde_json::json!(["name"]));
+        assert_eq!(preview["fields"]["name"]["placeholder"], "Ann");
+        assert_eq!(preview["rules"]["rules"]["name"], serde_json::json!(["Required"]));
+    }
+
+    #[test]
+    fn test_attachments() {
+        let builder = PromptBuilder::new::<bool>()
+            .attach_bytes("logo.png", "image/png", b"png")
+            .data_table(&[serde_json::json!({ "item": "Chair", "price": 40 })]);
+        assert_eq!(builder.blocks, vec![
+            ContentBlock::Attachment { filename: "logo.png".to_string(), content_type: Some("image/png".to_string()), data: Some("cG5n".to_string()), blob: None },
+            ContentBlock::Table(vec![serde_json::json!({ "item": "Chair", "price": 40 })]),
+        ]);
+    }
 }
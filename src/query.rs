@@ -0,0 +1,44 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{value_to_host, value_from_host, vec_parts_to_host};
+
+type Query = Box<dyn Fn() -> Value>;
+
+thread_local! {
+    // Queries registered by the latest call of a multistep function, by name.
+    static QUERIES: RefCell<BTreeMap<String, Query>> = RefCell::new(BTreeMap::new());
+}
+
+/// Lets the host ask a paused run for something, e.g. `expose_query("status", move || status.clone())`, without resuming it.
+/// Register queries early in the function, so a replay registers them again before it pauses;
+/// the latest registration under a name wins. For queries that only read `workflow::state`, see `#[middle_query]`.
+pub fn expose_query<T, S, F>(name: S, query: F) where T: Serialize, S: Into<String>, F: Fn() -> T + 'static {
+    let query = move || serde_json::to_value(query()).expect("query output could not be serialized into JSON");
+    QUERIES.with(|queries| queries.borrow_mut().insert(name.into(), Box::new(query)));
+}
+
+/// Answers a query registered with `expose_query`, called by the host with the query's name.
+/// Returns `None` if no query has that name.
+#[no_mangle]
+pub fn run_query(offset: u32, size: u32) -> u32 {
+    let name: String = value_from_host(offset, size);
+    let output: Option<Value> = QUERIES.with(|queries| queries.borrow().get(&name).map(|query| query()));
+    let (offset, size) = value_to_host(&output);
+    vec_parts_to_host(offset, size)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::query::*;
+
+    #[test]
+    fn test_expose_query() {
+        expose_query("status", || "shipping");
+        expose_query("status", || "delivered");
+        let output = QUERIES.with(|queries| queries.borrow().get("status").map(|query| query()));
+        assert_eq!(output, Some(Value::from("delivered")));
+    }
+}
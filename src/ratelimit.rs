@@ -0,0 +1,81 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use crate::{Resumable, pause};
+use crate::datetime::now_millis;
+
+/// A token bucket shared by every request bound to it.
+/// It holds up to `requests` tokens and refills at a steady rate of `requests` per `interval`.
+/// Cloning the limiter gives another handle to the same bucket.
+#[derive(Clone, Debug)]
+pub struct RateLimiter(Rc<RefCell<TokenBucket>>);
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    // Tokens added per millisecond.
+    refill_rate: f64,
+    tokens: f64,
+    last_refill_ms: u128,
+}
+
+impl TokenBucket {
+    fn refill(&mut self) {
+        let now = now_millis();
+        let elapsed = now.saturating_sub(self.last_refill_ms) as f64;
+        self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
+        self.last_refill_ms = now;
+    }
+}
+
+impl RateLimiter {
+    /// Allows `requests` requests per `interval`, with bursts of up to `requests` at once.
+    pub fn new(requests: u32, interval: Duration) -> Self {
+        let capacity = requests.max(1) as f64;
+        let interval_ms = interval.as_millis().max(1) as f64;
+        RateLimiter(Rc::new(RefCell::new(TokenBucket {
+            capacity,
+            refill_rate: capacity / interval_ms,
+            tokens: capacity,
+            last_refill_ms: now_millis(),
+        })))
+    }
+
+    /// Takes a token if one is available.
+    pub fn try_acquire(&self) -> bool {
+        let mut bucket = self.0.borrow_mut();
+        bucket.refill();
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How long until a token becomes available.
+    pub fn time_until_available(&self) -> Duration {
+        let mut bucket = self.0.borrow_mut();
+        bucket.refill();
+        if bucket.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        let missing = 1.0 - bucket.tokens;
+        Duration::from_millis((missing / bucket.refill_rate).ceil() as u64)
+    }
+
+    /// Takes a token, pausing the multi-step function until one is available.
+    pub fn acquire(&self) -> Resumable<()> {
+        loop {
+            if self.try_acquire() {
+                return Resumable::Ready(());
+            }
+            pause(self.time_until_available())?;
+        }
+    }
+}
+
+impl PartialEq for RateLimiter {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,134 @@
+//! Opt-in recording of host call results, for multistep functions that need every replay to see the same world.
+//! Call `replay::enable()` at the top of the function. From then on, the results of requests, prompts, `replay::now()`
+//! and `replay::random()` are recorded in call order, and later replays of the run get the recorded results
+//! instead of calling the host again, so requests aren't sent twice and the clock doesn't move between replays.
+//! Only those are recorded. Other host calls, such as spawned requests and their polls, `request_all`, events, messages
+//! and the randomness behind backoff jitter, still reach the host on every replay; wrap them in a `step` to run them once.
+
+use std::{cell::Cell, collections::hash_map::RandomState, hash::BuildHasher, time::SystemTime};
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{value_to_host, vec_parts_from_host, value_from_host, Timestamp};
+
+thread_local! {
+    // The number of the next recorded call, or `None` while replay is off.
+    static NEXT_SEQ: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Turns on recording for the rest of this call of the multistep function.
+/// Call it first thing, before anything that reads the clock or talks to the host, and on every replay.
+pub fn enable() {
+    NEXT_SEQ.with(|seq| seq.set(Some(0)));
+}
+
+/// Whether recording is on.
+pub fn is_enabled() -> bool {
+    NEXT_SEQ.with(Cell::get).is_some()
+}
+
+/// Turns recording off once the multistep function returns.
+pub(crate) fn disable() {
+    NEXT_SEQ.with(|seq| seq.set(None));
+}
+
+/// Runs `f` with recording off, for work whose result is recorded as a whole, such as a `step`.
+/// Replays that skip `f` then still number the calls after it the same way.
+pub(crate) fn without_recording<T, F: FnOnce() -> T>(f: F) -> T {
+    let seq = NEXT_SEQ.with(Cell::take);
+    let out = f();
+    NEXT_SEQ.with(|next| next.set(seq));
+    out
+}
+
+/// The current time, as first read by this run.
+pub fn now() -> Timestamp {
+    replayed_value("now", || SystemTime::now().into())
+}
+
+/// A random-looking number, the same one on every replay of this run.
+pub fn random() -> u64 {
+    replayed_value("random", || RandomState::new().hash_one(SystemTime::now()))
+}
+
+/// A value computed in the guest, recorded the first time and replayed after that.
+fn replayed_value<T, F>(kind: &str, f: F) -> T where T: Serialize + DeserializeOwned, F: FnOnce() -> T {
+    let seq = match next_seq() {
+        Some(seq) => seq,
+        None => return f(),
+    };
+    if let Some(bytes) = recorded(seq, kind) {
+        return rmp_serde::from_slice(&bytes).expect("replay: unable to read recorded value");
+    }
+    let value = f();
+    record(seq, kind, rmp_serde::to_vec(&value).expect("replay: unable to record value"));
+    value
+}
+
+/// Calls the host, or replays the recorded result of this call. `call` returns the offset `vec_parts_from_host` expects.
+/// `call` should pass its input to the host itself, as the host only frees the input when it's called.
+/// Results that `keep` rejects, such as a prompt that paused, aren't recorded, so the next replay asks the host again.
+pub(crate) fn host_call<T, C, K>(kind: &str, call: C, keep: K) -> T where T: DeserializeOwned, C: FnOnce() -> u32, K: FnOnce(&T) -> bool {
+    let seq = match next_seq() {
+        Some(seq) => seq,
+        None => {
+            let (offset, size) = vec_parts_from_host(call());
+            return value_from_host(offset, size);
+        },
+    };
+    if let Some(bytes) = recorded(seq, kind) {
+        return rmp_serde::from_slice(&bytes).expect("replay: unable to read recorded result");
+    }
+    let (offset, size) = vec_parts_from_host(call());
+    // Take the bytes rather than the value, so the result is recorded exactly as the host sent it.
+    let bytes = unsafe { Vec::from_raw_parts(offset as *mut u8, size as usize, size as usize) };
+    let out: T = rmp_serde::from_slice(&bytes).expect("from_host<T>: error reading from memory");
+    if keep(&out) {
+        record(seq, kind, bytes);
+    }
+    out
+}
+
+fn next_seq() -> Option<u32> {
+    NEXT_SEQ.with(|next| {
+        let seq = next.get()?;
+        next.set(Some(seq + 1));
+        Some(seq)
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recorded {
+    seq: u32,
+    // What kind of call was recorded, to catch a function that doesn't make the same calls in the same order.
+    kind: String,
+    #[serde(with = "serde_bytes")]
+    bytes: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct ReplayGetOut (Option<Recorded>);
+
+/// The recorded result of call `seq`, if the run got that far before.
+/// Panics if a different kind of call was recorded there, since the replay can't be trusted after that.
+fn recorded(seq: u32, kind: &str) -> Option<Vec<u8>> {
+    let offset = unsafe { host_replay_get(seq) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: ReplayGetOut = value_from_host(offset, size);
+    let recorded = out.0?;
+    if recorded.kind != kind {
+        panic!("replay: call {seq} was {} when recorded but is {kind} now; multistep functions must make the same calls in the same order", recorded.kind);
+    }
+    Some(recorded.bytes)
+}
+
+fn record(seq: u32, kind: &str, bytes: Vec<u8>) {
+    let (offset, size) = value_to_host(&Recorded { seq, kind: kind.to_string(), bytes });
+    unsafe { host_replay_record(offset, size) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_replay_get(seq: u32) -> u32;
+    pub fn host_replay_record(offset: u32, size: u32);
+}
@@ -0,0 +1,151 @@
+use std::{cell::Cell, fmt, time::Duration};
+
+use anyhow::Error;
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+
+use crate::Resumable;
+
+/// An error asking the host to run the function again after a while, e.g. `Err(RetryLater(Duration::from_secs(60)))?`
+/// when an upstream API is down for maintenance. It works as is or wrapped in an `anyhow::Error`.
+/// In a `#[middle_multistep_fn]` it works the same when the function ends with it. A `workflow::step` whose result
+/// holds one pauses for the delay and runs again, and in `workflow::with_retries` the next attempt waits for it instead of the policy's delay.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct RetryLater(pub Duration);
+
+thread_local! {
+    // The delay of the last `RetryLater` serialized, which is how `step` finds one in a result of any type.
+    static SERIALIZED: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+impl RetryLater {
+    pub fn delay(&self) -> Duration {
+        self.0
+    }
+    /// The delay of the last `RetryLater` serialized since this was last called, if any.
+    pub(crate) fn take_serialized() -> Option<Duration> {
+        SERIALIZED.with(Cell::take)
+    }
+    /// The delay asked for by an error or any of its causes.
+    pub(crate) fn find(err: &Error) -> Option<Duration> {
+        err.chain().find_map(|cause| cause.downcast_ref::<RetryLater>()).map(RetryLater::delay)
+    }
+}
+
+impl fmt::Display for RetryLater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Try again in {} seconds", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for RetryLater {}
+
+impl Serialize for RetryLater {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SERIALIZED.with(|delay| delay.set(Some(self.0)));
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RetryLater {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Duration::deserialize(deserializer).map(RetryLater)
+    }
+}
+
+/// Wraps the output of a `#[middle_fn]` or `#[middle_multistep_fn]`, to look for a `RetryLater` whatever the output's type.
+/// Outputs that can carry one implement `ProbeRetryLater`; all others fall back on `ProbeNoRetryLater`.
+#[doc(hidden)]
+pub struct RetryProbe<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ProbeRetryLater {
+    fn retry_later(&self) -> Option<Duration>;
+}
+
+impl<T> ProbeRetryLater for RetryProbe<'_, Result<T, RetryLater>> {
+    fn retry_later(&self) -> Option<Duration> {
+        self.0.as_ref().err().map(RetryLater::delay)
+    }
+}
+
+impl<T> ProbeRetryLater for RetryProbe<'_, Result<T, Error>> {
+    fn retry_later(&self) -> Option<Duration> {
+        self.0.as_ref().err().and_then(RetryLater::find)
+    }
+}
+
+impl<T> ProbeRetryLater for RetryProbe<'_, Resumable<Result<T, RetryLater>>> {
+    fn retry_later(&self) -> Option<Duration> {
+        match self.0 {
+            Resumable::Ready(Err(retry_later)) => Some(retry_later.delay()),
+            _ => None,
+        }
+    }
+}
+
+impl<T> ProbeRetryLater for RetryProbe<'_, Resumable<Result<T, Error>>> {
+    fn retry_later(&self) -> Option<Duration> {
+        match self.0 {
+            Resumable::Ready(Err(err)) => RetryLater::find(err),
+            _ => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeNoRetryLater {
+    fn retry_later(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<T> ProbeNoRetryLater for &RetryProbe<'_, T> {}
+
+/// Asks the host to run the function again after `delay`, if there is one.
+/// Called by the code `#[middle_fn]` and `#[middle_multistep_fn]` generate.
+#[doc(hidden)]
+pub fn request_retry(delay: Option<Duration>) {
+    if let Some(delay) = delay {
+        let millis = delay.as_millis().try_into().unwrap_or(u64::MAX);
+        unsafe { host_retry_later(millis) };
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_retry_later(millis: u64);
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use anyhow::Context;
+    use crate::retry_later::*;
+
+    #[test]
+    fn test_retry_probe() {
+        let delay = Duration::from_secs(30);
+        let output: Result<(), Error> = Err(RetryLater(delay)).context("Fetching the invoice");
+        assert_eq!(RetryProbe(&output).retry_later(), Some(delay));
+        let output: Result<u32, RetryLater> = Err(RetryLater(delay));
+        assert_eq!(RetryProbe(&output).retry_later(), Some(delay));
+        let output: Result<(), Error> = Err(Error::msg("Not found"));
+        assert_eq!(RetryProbe(&output).retry_later(), None);
+        assert_eq!((&RetryProbe(&"done")).retry_later(), None);
+        let output: Resumable<Result<(), Error>> = Resumable::Ready(Err(Error::new(RetryLater(delay))));
+        assert_eq!(RetryProbe(&output).retry_later(), Some(delay));
+        let output: Resumable<Result<(), RetryLater>> = Resumable::Pause;
+        assert_eq!(RetryProbe(&output).retry_later(), None);
+    }
+
+    #[test]
+    fn test_serialized_retry_later() {
+        let delay = Duration::from_secs(30);
+        RetryLater::take_serialized();
+        rmp_serde::to_vec(&Ok::<u32, RetryLater>(1)).unwrap();
+        assert_eq!(RetryLater::take_serialized(), None);
+        let encoded = rmp_serde::to_vec(&Err::<u32, RetryLater>(RetryLater(delay))).unwrap();
+        assert_eq!(RetryLater::take_serialized(), Some(delay));
+        assert_eq!(rmp_serde::from_slice::<Result<u32, RetryLater>>(&encoded).unwrap(), Err(RetryLater(delay)));
+    }
+}
@@ -0,0 +1,233 @@
+//! Recurring schedules for multistep functions that poll or report, e.g. "check this API every 15 minutes":
+//! `loop { pause_until_next(&Every::new(Duration::from_secs(15 * 60)))?; check_api()?; }`.
+
+use std::{str::FromStr, time::Duration};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{pause_until, Resumable, Timestamp};
+use crate::datetime::{civil_from_days, now_millis};
+use crate::workflow::{next_schedule_key, state};
+
+/// When something recurs. All times are in UTC.
+pub trait Schedule {
+    /// The first time the schedule comes around strictly after `after`, or `None` if it never does.
+    fn next_after(&self, after: Timestamp) -> Option<Timestamp>;
+}
+
+/// Pauses until the next time `schedule` comes around.
+/// The time is worked out the first time this is reached and kept in the run's state, so replays wait for the same time.
+/// Schedules that never come around again, like one for February 30th, never resume.
+pub fn pause_until_next<S: Schedule>(schedule: &S) -> Resumable<()> {
+    let stored = state::<Timestamp, _>(next_schedule_key());
+    let next = match stored.get() {
+        Some(next) => next,
+        None => {
+            let now = Timestamp::from_millis(now_millis().try_into().unwrap_or(u64::MAX));
+            let next = schedule.next_after(now).unwrap_or(Timestamp::from_millis(u64::MAX));
+            stored.set(&next);
+            next
+        },
+    };
+    pause_until(next)
+}
+
+/// A fixed interval, counted from an anchor, by default the unix epoch.
+/// Counting from a fixed point keeps the times on the clock, e.g. every 15 minutes is :00, :15, :30 and :45.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Every {
+    interval_millis: u64,
+    anchor: Timestamp,
+}
+
+impl Every {
+    /// Intervals shorter than a millisecond count as a millisecond.
+    pub fn new(interval: Duration) -> Self {
+        let interval_millis = interval.as_millis().clamp(1, u64::MAX.into()) as u64;
+        Self { interval_millis, anchor: Timestamp::from_millis(0) }
+    }
+    /// Counts the interval from `anchor` instead of the unix epoch, e.g. every 24 hours from 9am.
+    pub fn starting_at<T: Into<Timestamp>>(mut self, anchor: T) -> Self {
+        self.anchor = anchor.into();
+        self
+    }
+}
+
+impl Schedule for Every {
+    fn next_after(&self, after: Timestamp) -> Option<Timestamp> {
+        let (after, anchor) = (after.as_millis(), self.anchor.as_millis());
+        if after < anchor {
+            return Some(self.anchor);
+        }
+        let intervals = (after - anchor) / self.interval_millis + 1;
+        let next = anchor.checked_add(intervals.checked_mul(self.interval_millis)?)?;
+        Some(Timestamp::from_millis(next))
+    }
+}
+
+/// A cron expression with five fields: minute, hour, day of the month, month and day of the week, e.g. "0 9 1 * *".
+/// Fields take `*`, numbers, ranges like `1-5`, lists like `1,15` and steps like `*/15`. Sunday is 0 or 7.
+/// As in cron, when both days are restricted, a day matching either of them counts.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    expression: String,
+
+    // One bit per allowed value.
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+
+    // Whether the day fields were `*`, which changes how they combine.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("Invalid cron expression {expression}, expected 5 fields"));
+        };
+        let mut weekdays = parse_field(weekdays, 0, 7)?;
+        // Sunday can be written as 7.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays,
+            any_day: days == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (_, month, day) = civil_from_days(days_since_epoch);
+        // The unix epoch was a Thursday.
+        let weekday = (days_since_epoch + 4) % 7;
+        let day_matches = self.days & (1 << day) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        };
+        self.months & (1 << month) != 0 && day_matches
+    }
+}
+
+/// Parses one field of a cron expression into a bit per allowed value.
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64, String> {
+    let invalid = || format!("Invalid cron field {field}, expected values from {min} to {max}");
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?),
+            // A single value with a step runs to the end, e.g. 5/15 is 5, 20, 35 and 50.
+            None if part.contains('/') => (range.parse().map_err(|_| invalid())?, max),
+            None => {
+                let value = range.parse().map_err(|_| invalid())?;
+                (value, value)
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl Schedule for Cron {
+    fn next_after(&self, after: Timestamp) -> Option<Timestamp> {
+        const MINUTES_PER_DAY: u64 = 24 * 60;
+        let first = after.as_secs() / 60 + 1;
+        let first_day = first / MINUTES_PER_DAY;
+        // Every valid expression matches within a few years, February 29th on a given weekday being the rarest.
+        for day in first_day..first_day + 366 * 28 {
+            if !self.matches_day(day) {
+                continue;
+            }
+            let start = if day == first_day { first % MINUTES_PER_DAY } else { 0 };
+            let minute = (start..MINUTES_PER_DAY).find(|minute| {
+                self.hours & (1 << (minute / 60)) != 0 && self.minutes & (1 << (minute % 60)) != 0
+            });
+            if let Some(minute) = minute {
+                return Some(Timestamp::from_millis((day * MINUTES_PER_DAY + minute) * 60_000));
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = String;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        Self::parse(&expression)
+    }
+}
+
+impl From<Cron> for String {
+    fn from(cron: Cron) -> Self {
+        cron.expression
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::schedule::*;
+
+    // 2024-05-31T17:07:30Z, a Friday.
+    const NOW: u64 = 1717175250;
+
+    fn next(schedule: &impl Schedule) -> u64 {
+        schedule.next_after(Timestamp::from(NOW)).unwrap().as_secs()
+    }
+
+    #[test]
+    fn test_every() {
+        let every = Every::new(Duration::from_secs(15 * 60));
+        assert_eq!(next(&every), 1717175700); // 17:15
+        let every = Every::new(Duration::from_secs(3600)).starting_at(NOW + 10);
+        assert_eq!(next(&every), NOW + 10);
+        assert_eq!(every.next_after(Timestamp::from(NOW + 10)).unwrap().as_secs(), NOW + 3610);
+    }
+
+    #[test]
+    fn test_cron() {
+        assert_eq!(next(&Cron::parse("*/15 * * * *").unwrap()), 1717175700); // 17:15
+        assert_eq!(next(&Cron::parse("0 9 1 * *").unwrap()), 1717232400); // June 1st, 9:00
+        assert_eq!(next(&Cron::parse("30 8 * * 1-5").unwrap()), 1717403400); // Monday June 3rd, 8:30
+        assert_eq!(next(&Cron::parse("0 0 * * 7").unwrap()), 1717286400); // Sunday June 2nd
+        assert_eq!(next(&Cron::parse("7 17 31 5 *").unwrap()), 1748711220); // May 31st 2025, 17:07
+        assert_eq!(Cron::parse("0 0 30 2 *").unwrap().next_after(Timestamp::from(NOW)), None);
+
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert_eq!(serde_json::to_value(Cron::parse("0  9 * * *").unwrap()).unwrap(), "0 9 * * *");
+    }
+}
@@ -0,0 +1,356 @@
+use schemars::schema::{RootSchema, Schema, SchemaObject, SubschemaValidation};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::Choice;
+
+/// Schema extension that shows a field only while another field has a given value.
+/// Holds `{ "field": name, "equals": value }`.
+pub(crate) const SHOW_IF_EXTENSION: &str = "x-show-if";
+
+/// Schema extension naming the section a field belongs to.
+pub(crate) const SECTION_EXTENSION: &str = "x-section";
+
+/// Schema extension on the form listing how its sections are shown.
+pub(crate) const SECTIONS_EXTENSION: &str = "x-sections";
+
+/// Schema extension on the form listing its fields in the order they're shown in,
+/// as the properties of a schema are kept sorted by name.
+pub(crate) const FIELD_ORDER_EXTENSION: &str = "x-field-order";
+
+/// A group of fields shown under a heading, e.g. "Shipping address".
+/// The host shows a section where its first field is, with every field of the section below it.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Section {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    // Whether the user can fold the section away.
+    collapsible: bool,
+
+    // Whether the section starts out folded.
+    collapsed: bool,
+}
+
+impl Section {
+    pub fn new<S: Into<String>>(title: S) -> Self {
+        Self { title: title.into(), description: None, collapsible: false, collapsed: false }
+    }
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+    /// Lets the user fold the section away.
+    pub fn collapsible(mut self) -> Self {
+        self.collapsible = true;
+        self
+    }
+    /// Starts the section out folded, e.g. for advanced settings most users leave alone.
+    pub fn collapsed(mut self) -> Self {
+        self.collapsible = true;
+        self.collapsed = true;
+        self
+    }
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+impl From<&str> for Section {
+    fn from(title: &str) -> Self {
+        Self::new(title)
+    }
+}
+
+/// Builds the schema of a form whose fields are only known at runtime.
+/// Methods like `required` and `min` apply to the field added last:
+/// `SchemaBuilder::new().text("name").required().number("qty").min(1.0).build()`.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SchemaBuilder {
+    title: Option<String>,
+    description: Option<String>,
+
+    // Fields in the order they were added, which is the order the host shows them in.
+    fields: Vec<(String, Map<String, Value>)>,
+
+    required: Vec<String>,
+
+    sections: Vec<Section>,
+
+    // Title of the section fields are added to.
+    current_section: Option<String>,
+}
+
+impl SchemaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a field with a hand-written schema, for anything the other methods don't cover.
+    /// The schema is a JSON object, e.g. `json!({ "type": "string" }).as_object().cloned().unwrap_or_default()`.
+    pub fn field<S: Into<String>>(mut self, name: S, mut schema: Map<String, Value>) -> Self {
+        let name = name.into();
+        if let Some(section) = &self.current_section {
+            schema.insert(SECTION_EXTENSION.to_string(), Value::String(section.clone()));
+        }
+        self.fields.push((name, schema));
+        self
+    }
+    /// Starts a section. Fields added after it belong to it, up to the next section or `end_section`.
+    pub fn section<S: Into<Section>>(mut self, section: S) -> Self {
+        let section = section.into();
+        self.current_section = Some(section.title.clone());
+        match self.sections.iter_mut().find(|s| s.title == section.title) {
+            Some(existing) => *existing = section,
+            None => self.sections.push(section),
+        }
+        self
+    }
+    /// Ends the current section, so the fields added after it aren't in any.
+    pub fn end_section(mut self) -> Self {
+        self.current_section = None;
+        self
+    }
+    pub fn text<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "string" })))
+    }
+    pub fn number<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "number" })))
+    }
+    pub fn integer<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "integer" })))
+    }
+    pub fn boolean<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "boolean" })))
+    }
+    pub fn date<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "string", "format": "date" })))
+    }
+    pub fn date_time<S: Into<String>>(self, name: S) -> Self {
+        self.field(name, object(json!({ "type": "string", "format": "date-time" })))
+    }
+    /// Adds a field holding one of `options`.
+    pub fn select<S: Into<String>>(self, name: S, options: &[Choice]) -> Self {
+        self.field(name, object(json!({ "type": "string", "oneOf": one_of(options) })))
+    }
+    /// Adds a field holding any number of `options`.
+    pub fn multi_select<S: Into<String>>(self, name: S, options: &[Choice]) -> Self {
+        self.field(name, object(json!({ "type": "array", "uniqueItems": true, "items": { "oneOf": one_of(options) } })))
+    }
+
+    /// Sets a keyword of the schema of the field added last.
+    fn set(mut self, keyword: &str, value: Value) -> Self {
+        if let Some((_, schema)) = self.fields.last_mut() {
+            schema.insert(keyword.to_string(), value);
+        }
+        self
+    }
+    pub fn required(mut self) -> Self {
+        if let Some((name, _)) = self.fields.last() {
+            if !self.required.contains(name) {
+                self.required.push(name.clone());
+            }
+        }
+        self
+    }
+    /// Sets the label the host shows for the field. Defaults to the field's name.
+    pub fn label<S: Into<String>>(self, label: S) -> Self {
+        self.set("title", Value::String(label.into()))
+    }
+    pub fn help<S: Into<String>>(self, description: S) -> Self {
+        self.set("description", Value::String(description.into()))
+    }
+    pub fn default_value(self, value: Value) -> Self {
+        self.set("default", value)
+    }
+    pub fn min(self, min: f64) -> Self {
+        self.set("minimum", json!(min))
+    }
+    pub fn max(self, max: f64) -> Self {
+        self.set("maximum", json!(max))
+    }
+    pub fn min_length(self, min: u32) -> Self {
+        self.set("minLength", json!(min))
+    }
+    pub fn max_length(self, max: u32) -> Self {
+        self.set("maxLength", json!(max))
+    }
+    pub fn pattern<S: Into<String>>(self, pattern: S) -> Self {
+        self.set("pattern", Value::String(pattern.into()))
+    }
+    /// Shows the field only while `field` has the value `equals`, e.g. an "Other reason" box when reason is "other".
+    /// A hidden field is left out of the submission, even when it's required.
+    pub fn show_if<S: Into<String>>(self, field: S, equals: Value) -> Self {
+        self.set(SHOW_IF_EXTENSION, json!({ "field": field.into(), "equals": equals }))
+    }
+
+    pub fn build(self) -> RootSchema {
+        let order: Vec<&str> = self.fields.iter().map(|(name, _)| name.as_str()).collect();
+        let order = json!(order);
+        let properties: Map<String, Value> = self.fields.into_iter()
+            .map(|(name, schema)| (name, Value::Object(schema)))
+            .collect();
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "required": self.required,
+            "properties": properties,
+            FIELD_ORDER_EXTENSION: order,
+        });
+        if let Some(title) = self.title {
+            schema["title"] = Value::String(title);
+        }
+        if let Some(description) = self.description {
+            schema["description"] = Value::String(description);
+        }
+        if !self.sections.is_empty() {
+            schema[SECTIONS_EXTENSION] = json!(self.sections);
+        }
+        serde_json::from_value(schema).expect("SchemaBuilder: invalid schema")
+    }
+}
+
+/// The fields of a schema written with `json!`, which is always an object.
+fn object(schema: Value) -> Map<String, Value> {
+    match schema {
+        Value::Object(schema) => schema,
+        other => unreachable!("schema literals are objects, not {other}"),
+    }
+}
+
+/// Adds UI metadata to the schema of a field. Called by the code `#[derive(MiddleForm)]` generates.
+/// Labels and help become the schema's title and description; placeholders and widgets become the
+/// `x-placeholder` and `x-widget` extensions, which the host treats like the matching `FieldHints`.
+/// Conditions and sections become the `x-show-if` and `x-section` extensions.
+#[doc(hidden)]
+pub fn form_field_schema(schema: Schema, label: Option<&str>, placeholder: Option<&str>, help: Option<&str>, widget: Option<&str>, show_if: Option<(&str, Value)>, section: Option<&str>) -> Schema {
+    let mut schema = schema.into_object();
+    // Keywords next to a $ref are ignored, so wrap references first.
+    if schema.reference.is_some() {
+        schema = SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation { all_of: Some(vec![schema.into()]), ..Default::default() })),
+            ..Default::default()
+        };
+    }
+    if let Some(label) = label {
+        schema.metadata().title = Some(label.to_string());
+    }
+    if let Some(help) = help {
+        schema.metadata().description = Some(help.to_string());
+    }
+    if let Some(placeholder) = placeholder {
+        schema.extensions.insert("x-placeholder".to_string(), Value::String(placeholder.to_string()));
+    }
+    if let Some(widget) = widget {
+        schema.extensions.insert("x-widget".to_string(), Value::String(widget.to_string()));
+    }
+    if let Some((field, equals)) = show_if {
+        schema.extensions.insert(SHOW_IF_EXTENSION.to_string(), json!({ "field": field, "equals": equals }));
+    }
+    if let Some(section) = section {
+        schema.extensions.insert(SECTION_EXTENSION.to_string(), Value::String(section.to_string()));
+    }
+    schema.into()
+}
+
+/// Lists how the sections of a form are shown. Called by the code `#[derive(MiddleForm)]` generates.
+#[doc(hidden)]
+pub fn form_sections(schema: Schema, sections: Vec<Section>) -> Schema {
+    let mut schema = schema.into_object();
+    schema.extensions.insert(SECTIONS_EXTENSION.to_string(), json!(sections));
+    schema.into()
+}
+
+/// Each option as a `const` with a title, which is how JSON Schema labels the values of an enum.
+fn one_of(options: &[Choice]) -> Vec<Value> {
+    options.iter()
+        .map(|option| {
+            let mut schema = json!({ "const": option.value(), "title": option.label() });
+            if let Some(description) = option.description() {
+                schema["description"] = Value::String(description.to_string());
+            }
+            schema
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::schema::*;
+
+    #[test]
+    fn test_schema_builder() {
+        let schema = SchemaBuilder::new()
+            .title("Order")
+            .text("name").required().max_length(40)
+            .integer("qty").min(1.0).label("Quantity")
+            .select("env", &[Choice::new("prod", "Production"), Choice::new("dev", "Development")]).required()
+            .text("reason").required().show_if("env", json!("prod"))
+            .section(Section::new("Advanced").collapsed())
+            .boolean("dry_run")
+            .end_section()
+            .text("notes")
+            .build();
+        let schema = serde_json::to_value(schema).unwrap();
+        assert_eq!(schema["title"], "Order");
+        assert_eq!(schema["required"], json!(["env", "name", "reason"]));
+        assert_eq!(schema["x-field-order"], json!(["name", "qty", "env", "reason", "dry_run", "notes"]));
+        assert_eq!(schema["properties"]["dry_run"]["x-section"], "Advanced");
+        assert_eq!(schema["properties"]["notes"].get("x-section"), None);
+        assert_eq!(schema["x-sections"], json!([{ "title": "Advanced", "collapsible": true, "collapsed": true }]));
+        assert_eq!(schema["properties"]["reason"]["x-show-if"], json!({ "field": "env", "equals": "prod" }));
+        assert_eq!(schema["properties"]["name"]["maxLength"], 40);
+        assert_eq!(schema["properties"]["qty"], json!({ "type": "integer", "minimum": 1.0, "title": "Quantity" }));
+        assert_eq!(schema["properties"]["env"]["oneOf"][1], json!({ "const": "dev", "title": "Development" }));
+    }
+
+    #[test]
+    fn test_hand_written_field() {
+        let schema = json!({ "type": "string", "format": "email" }).as_object().cloned().unwrap_or_default();
+        let schema = serde_json::to_value(SchemaBuilder::new().field("email", schema).build()).unwrap();
+        assert_eq!(schema["properties"]["email"], json!({ "type": "string", "format": "email" }));
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Address {
+        city: String,
+    }
+
+    #[derive(MiddleForm, Deserialize)]
+    #[allow(dead_code)]
+    #[form(section(title = "Shipping", description = "Where we send it", collapsible))]
+    struct Contact {
+        #[form(label = "Email address", placeholder = "you@example.com")]
+        email: String,
+        #[form(widget = "textarea", help = "Anything else?", show_if(contact_me = true))]
+        notes: Option<String>,
+        contact_me: bool,
+        #[form(label = "Shipping address", section = "Shipping")]
+        address: Address,
+        age: u32,
+    }
+
+    #[test]
+    fn test_middle_form() {
+        let schema = serde_json::to_value(schemars::schema_for!(Contact)).unwrap();
+        assert_eq!(schema["title"], "Contact");
+        assert_eq!(schema["required"], json!(["address", "age", "contact_me", "email"]));
+        assert_eq!(schema["properties"]["email"], json!({ "type": "string", "title": "Email address", "x-placeholder": "you@example.com" }));
+        assert_eq!(schema["properties"]["notes"]["x-widget"], "TextArea");
+        assert_eq!(schema["properties"]["notes"]["description"], "Anything else?");
+        assert_eq!(schema["properties"]["notes"]["x-show-if"], json!({ "field": "contact_me", "equals": true }));
+        assert_eq!(schema["properties"]["address"], json!({ "title": "Shipping address", "allOf": [{ "$ref": "#/definitions/Address" }], "x-section": "Shipping" }));
+        assert_eq!(schema["x-sections"], json!([{ "title": "Shipping", "description": "Where we send it", "collapsible": true, "collapsed": false }]));
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::Schema};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+/// Schema extension telling the host to mask a field and keep its value out of logs.
+pub(crate) const SECRET_EXTENSION: &str = "x-secret";
+
+/// A value, such as an API key or password, that is kept out of `Debug` output.
+/// As a prompt field, it renders as a masked input and the host leaves it out of its logs.
+/// It still serializes as the plain value, so be careful where it's sent.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+    /// The secret value. Named so uses stand out in review.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Secret<T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        format!("Secret_{}", T::schema_name())
+    }
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = T::json_schema(generator).into_object();
+        schema.format = Some("password".to_string());
+        schema.metadata().write_only = true;
+        schema.extensions.insert(SECRET_EXTENSION.to_string(), Value::Bool(true));
+        schema.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::secret::*;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Login {
+        username: String,
+        password: Secret<String>,
+    }
+
+    #[test]
+    fn test_secret() {
+        let secret = Secret::new("hunter2".to_string());
+        assert_eq!(format!("{secret:?}"), "Secret([REDACTED])");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"hunter2\"");
+
+        let schema = serde_json::to_value(schemars::schema_for!(Login)).unwrap();
+        let password = &schema["properties"]["password"];
+        assert_eq!(password["type"], "string");
+        assert_eq!(password["format"], "password");
+        assert_eq!(password["x-secret"], true);
+    }
+}
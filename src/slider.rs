@@ -0,0 +1,144 @@
+use std::fmt;
+
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::{InstanceType, NumberValidation, Schema, SchemaObject}};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+/// An integer schema with bounds, rendered with the given widget.
+fn bounded_schema(min: i64, max: i64, widget: &str) -> Schema {
+    let mut schema = SchemaObject {
+        instance_type: Some(InstanceType::Integer.into()),
+        number: Some(Box::new(NumberValidation {
+            minimum: Some(min as f64),
+            maximum: Some(max as f64),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    schema.extensions.insert("x-widget".to_string(), Value::String(widget.to_string()));
+    schema.into()
+}
+
+/// A whole number from `MIN` to `MAX` inclusive, rendered as a slider.
+/// Numbers outside the bounds don't deserialize.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[serde(try_from = "i64", into = "i64")]
+pub struct Slider<const MIN: i64, const MAX: i64>(i64);
+
+impl<const MIN: i64, const MAX: i64> Slider<MIN, MAX> {
+    /// Returns `None` for numbers outside the bounds.
+    pub fn new(value: i64) -> Option<Self> {
+        (MIN..=MAX).contains(&value).then_some(Self(value))
+    }
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> TryFrom<i64> for Slider<MIN, MAX> {
+    type Error = String;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        Self::new(value).ok_or_else(|| format!("{value} is out of range, expected {MIN} to {MAX}"))
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> From<Slider<MIN, MAX>> for i64 {
+    fn from(value: Slider<MIN, MAX>) -> Self {
+        value.0
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> fmt::Display for Slider<MIN, MAX> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> JsonSchema for Slider<MIN, MAX> {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        format!("Slider_{MIN}_{MAX}")
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        bounded_schema(MIN, MAX, "Slider")
+    }
+}
+
+/// A rating from 1 to `N`, rendered as a row of `N` stars.
+/// Use an `Option<Rating<N>>` to let the user leave it unrated.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Rating<const N: u8>(u8);
+
+impl<const N: u8> Rating<N> {
+    /// Returns `None` for 0 and ratings above `N`.
+    pub fn new(stars: u8) -> Option<Self> {
+        (1..=N).contains(&stars).then_some(Self(stars))
+    }
+    pub fn stars(&self) -> u8 {
+        self.0
+    }
+}
+
+impl<const N: u8> TryFrom<u8> for Rating<N> {
+    type Error = String;
+
+    fn try_from(stars: u8) -> Result<Self, Self::Error> {
+        Self::new(stars).ok_or_else(|| format!("{stars} is out of range, expected a rating from 1 to {N}"))
+    }
+}
+
+impl<const N: u8> From<Rating<N>> for u8 {
+    fn from(rating: Rating<N>) -> Self {
+        rating.0
+    }
+}
+
+impl<const N: u8> fmt::Display for Rating<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{N}", self.0)
+    }
+}
+
+impl<const N: u8> JsonSchema for Rating<N> {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        format!("Rating_{N}")
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        bounded_schema(1, N.into(), "Rating")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use crate::slider::*;
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Feedback {
+        volume: Slider<0, 11>,
+        rating: Option<Rating<5>>,
+    }
+
+    #[test]
+    fn test_slider() {
+        let feedback: Feedback = serde_json::from_value(json!({ "volume": 11, "rating": 4 })).unwrap();
+        assert_eq!(feedback.volume.value(), 11);
+        assert_eq!(feedback.rating.unwrap().to_string(), "4/5");
+        assert!(serde_json::from_value::<Feedback>(json!({ "volume": 12 })).is_err());
+        assert!(serde_json::from_value::<Feedback>(json!({ "volume": 1, "rating": 0 })).is_err());
+        assert_eq!(serde_json::to_value(Slider::<-5, 5>::new(-3).unwrap()).unwrap(), json!(-3));
+
+        let schema = serde_json::to_value(schemars::schema_for!(Feedback)).unwrap();
+        assert_eq!(schema["properties"]["volume"], json!({ "type": "integer", "minimum": 0.0, "maximum": 11.0, "x-widget": "Slider" }));
+        assert_eq!(schema["properties"]["rating"]["maximum"], 5.0);
+        assert_eq!(schema["properties"]["rating"]["x-widget"], "Rating");
+    }
+}
@@ -0,0 +1,272 @@
+//! Calls SOAP 1.1 and 1.2 services. Only available with the `xml` feature.
+
+use std::fmt;
+
+use quick_xml::{Reader, events::Event, escape::{escape, unescape}};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{RequestBuilder, RequestError};
+use crate::headers;
+
+const SOAP_11_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const SOAP_12_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+/// Which version of SOAP the service speaks. They differ in envelope namespace and in how the action is sent.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SoapVersion {
+    V11,
+    V12,
+}
+
+/// A SOAP call, sent as an HTTP POST.
+pub struct SoapRequest {
+    endpoint: String,
+    version: SoapVersion,
+    action: Option<String>,
+    // XML placed inside the envelope's Header element, e.g. WS-Security blocks.
+    header_blocks: Vec<String>,
+    // XML placed inside the envelope's Body element.
+    body: String,
+    // Set if the body couldn't be serialized; reported when the call is made.
+    error: Option<String>,
+}
+
+/// A SOAP Fault returned by the service, for either SOAP version.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SoapFault {
+    // `faultcode` in SOAP 1.1, `Code/Value` in SOAP 1.2, e.g. "soap:Server".
+    pub code: String,
+
+    // `faultstring` in SOAP 1.1, the first `Reason/Text` in SOAP 1.2.
+    pub reason: String,
+
+    // Raw XML of the `detail` element, if any.
+    pub detail: Option<String>,
+}
+
+/// Why a SOAP call failed.
+#[derive(Debug)]
+pub enum SoapError {
+    // The request never got a response.
+    Transport(RequestError),
+
+    // The service answered with a Fault.
+    Fault(SoapFault),
+
+    // The service answered with a status that isn't 2xx, and no Fault to explain it.
+    Status(u32, String),
+
+    // The response wasn't a SOAP envelope, or its body didn't match the expected type.
+    Decode(String),
+}
+
+impl fmt::Display for SoapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoapError::Transport(err) => write!(f, "SOAP request failed: {err}"),
+            SoapError::Fault(fault) => write!(f, "SOAP fault {}: {}", fault.code, fault.reason),
+            SoapError::Status(code, body) => write!(f, "SOAP service returned HTTP {code}: {body}"),
+            SoapError::Decode(err) => write!(f, "Unable to decode SOAP response: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SoapError {}
+
+impl SoapRequest {
+    pub fn new<S: Into<String>>(endpoint: S, version: SoapVersion) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            version,
+            action: None,
+            header_blocks: vec![],
+            body: String::new(),
+            error: None,
+        }
+    }
+    /// Sets the action, sent as the SOAPAction header in SOAP 1.1 and as a Content-Type parameter in SOAP 1.2.
+    pub fn action<S: Into<String>>(mut self, action: S) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+    /// Adds raw XML to the envelope's Header element.
+    pub fn header_block<S: Into<String>>(mut self, xml: S) -> Self {
+        self.header_blocks.push(xml.into());
+        self
+    }
+    /// Sets the raw XML inside the envelope's Body element.
+    pub fn body<S: Into<String>>(mut self, xml: S) -> Self {
+        self.body = xml.into();
+        self
+    }
+    /// Serializes `value` into the envelope's Body element.
+    pub fn body_from<T: Serialize>(mut self, value: &T) -> Self {
+        match quick_xml::se::to_string(value) {
+            Ok(xml) => self.body = xml,
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        self
+    }
+
+    /// Wraps the header blocks and body in an envelope.
+    pub fn envelope(&self) -> String {
+        let namespace = match self.version {
+            SoapVersion::V11 => SOAP_11_NAMESPACE,
+            SoapVersion::V12 => SOAP_12_NAMESPACE,
+        };
+        let header = match self.header_blocks.is_empty() {
+            true => String::new(),
+            false => format!("<soap:Header>{}</soap:Header>", self.header_blocks.concat()),
+        };
+        format!(r#"<?xml version="1.0" encoding="utf-8"?><soap:Envelope xmlns:soap="{namespace}">{header}<soap:Body>{}</soap:Body></soap:Envelope>"#, self.body)
+    }
+
+    /// Builds the HTTP request, for callers that need to tweak it further before calling it.
+    pub fn to_request(&self) -> RequestBuilder {
+        let builder = RequestBuilder::post(self.endpoint.clone()).with_body(self.envelope());
+        let builder = match self.version {
+            SoapVersion::V11 => {
+                let builder = builder.with_header(headers::CONTENT_TYPE, "text/xml; charset=utf-8");
+                let action = self.action.as_deref().unwrap_or("");
+                builder.with_header("SOAPAction", format!("\"{action}\""))
+            },
+            SoapVersion::V12 => match &self.action {
+                Some(action) => builder.with_header(headers::CONTENT_TYPE, format!("application/soap+xml; charset=utf-8; action=\"{action}\"")),
+                None => builder.with_header(headers::CONTENT_TYPE, "application/soap+xml; charset=utf-8"),
+            },
+        };
+        match &self.error {
+            Some(err) => builder.with_error(RequestError::Serde(err.clone())),
+            None => builder,
+        }
+    }
+
+    /// Makes the call and returns the raw XML inside the response's Body element.
+    pub fn send_raw(&self) -> Result<String, SoapError> {
+        let response = self.to_request().call().map_err(SoapError::Transport)?;
+        read_response(response.code(), &response.text())
+    }
+
+    /// Makes the call and deserializes the element inside the response's Body into `T`.
+    /// Namespace prefixes are ignored when matching element names to fields.
+    pub fn send<T: DeserializeOwned>(&self) -> Result<T, SoapError> {
+        let body = self.send_raw()?;
+        quick_xml::de::from_str(&body).map_err(|err| SoapError::Decode(err.to_string()))
+    }
+}
+
+/// Escapes text for use in hand-written body XML.
+pub fn escape_text(text: &str) -> String {
+    escape(text).into_owned()
+}
+
+/// Returns the raw XML inside the Body of a response, or the Fault or HTTP status that failed the call.
+fn read_response(code: u32, text: &str) -> Result<String, SoapError> {
+    let success = (200..300).contains(&code);
+    let body = match envelope_body(text) {
+        Ok(body) => body,
+        Err(_) if !success => return Err(SoapError::Status(code, text.to_string())),
+        Err(err) => return Err(SoapError::Decode(err)),
+    };
+    match parse_fault(&body) {
+        Some(fault) => Err(SoapError::Fault(fault)),
+        None if !success => Err(SoapError::Status(code, text.to_string())),
+        None => Ok(body),
+    }
+}
+
+/// Returns the raw XML inside an envelope's Body element.
+fn envelope_body(xml: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().map_err(|err| err.to_string())? {
+            Event::Start(start) if start.local_name().as_ref() == b"Body" => {
+                let end = start.to_end().into_owned();
+                let span = reader.read_to_end(end.name()).map_err(|err| err.to_string())?;
+                return Ok(xml[span.start as usize..span.end as usize].trim().to_string());
+            },
+            Event::Empty(start) if start.local_name().as_ref() == b"Body" => return Ok(String::new()),
+            Event::Eof => return Err("no SOAP Body element".to_string()),
+            _ => {},
+        }
+    }
+}
+
+/// Parses the Fault at the start of a Body, in either SOAP version.
+fn parse_fault(body: &str) -> Option<SoapFault> {
+    let mut reader = Reader::from_str(body);
+    let mut fault = None;
+    loop {
+        let start = match reader.read_event().ok()? {
+            Event::Start(start) => start,
+            Event::Eof => return fault,
+            _ => continue,
+        };
+        let name = start.local_name().as_ref().to_vec();
+        if fault.is_none() {
+            // Only a Fault as the first element of the Body counts.
+            if name != b"Fault" {
+                return None;
+            }
+            fault = Some(SoapFault::default());
+            continue;
+        }
+        let fault = fault.as_mut()?;
+        let end = start.to_end().into_owned();
+        match name.as_slice() {
+            b"faultcode" | b"Value" if fault.code.is_empty() => {
+                let text = reader.read_text(end.name()).ok()?;
+                fault.code = unescape(text.trim()).ok()?.into_owned();
+            },
+            b"faultstring" | b"Text" if fault.reason.is_empty() => {
+                let text = reader.read_text(end.name()).ok()?;
+                fault.reason = unescape(text.trim()).ok()?.into_owned();
+            },
+            b"detail" | b"Detail" => {
+                let text = reader.read_text(end.name()).ok()?;
+                fault.detail = Some(text.trim().to_string());
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::soap::*;
+
+    #[test]
+    fn test_envelope_body() {
+        let request = SoapRequest::new("https://example.com/ws", SoapVersion::V11).body("<m:GetPrice xmlns:m=\"urn:x\"><m:Item>Apples</m:Item></m:GetPrice>");
+        let envelope = request.envelope();
+        assert_eq!(envelope_body(&envelope).unwrap(), "<m:GetPrice xmlns:m=\"urn:x\"><m:Item>Apples</m:Item></m:GetPrice>");
+        assert_eq!(parse_fault(&envelope_body(&envelope).unwrap()), None);
+    }
+
+    #[test]
+    fn test_parse_fault() {
+        let v11 = "<soap:Fault><faultcode>soap:Server</faultcode><faultstring>Out of &amp; stock</faultstring><detail><e:Code>7</e:Code></detail></soap:Fault>";
+        assert_eq!(parse_fault(v11), Some(SoapFault {
+            code: "soap:Server".to_string(),
+            reason: "Out of & stock".to_string(),
+            detail: Some("<e:Code>7</e:Code>".to_string()),
+        }));
+
+        let v12 = "<env:Fault><env:Code><env:Value>env:Sender</env:Value><env:Subcode><env:Value>m:Bad</env:Value></env:Subcode></env:Code>\
+            <env:Reason><env:Text xml:lang=\"en\">Bad input</env:Text></env:Reason></env:Fault>";
+        assert_eq!(parse_fault(v12), Some(SoapFault { code: "env:Sender".to_string(), reason: "Bad input".to_string(), detail: None }));
+    }
+
+    #[test]
+    fn test_read_response() {
+        let ok = SoapRequest::new("https://example.com/ws", SoapVersion::V11).body("<m:Price>1.5</m:Price>").envelope();
+        assert_eq!(read_response(200, &ok).unwrap(), "<m:Price>1.5</m:Price>");
+        assert!(matches!(read_response(500, &ok), Err(SoapError::Status(500, _))));
+        assert!(matches!(read_response(502, "Bad Gateway"), Err(SoapError::Status(502, _))));
+
+        let fault = SoapRequest::new("https://example.com/ws", SoapVersion::V11)
+            .body("<soap:Fault><faultcode>soap:Server</faultcode><faultstring>Down</faultstring></soap:Fault>")
+            .envelope();
+        assert!(matches!(read_response(500, &fault), Err(SoapError::Fault(_))));
+    }
+}
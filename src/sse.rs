@@ -0,0 +1,178 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{RequestBuilder, RequestError, value_to_host, vec_parts_from_host, value_from_host};
+
+/// A single Server-Sent Event.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct SseEvent {
+    // The last event ID seen on the stream, if any.
+    pub id: Option<String>,
+
+    // Event type. Defaults to "message" when the server doesn't name one.
+    pub event: String,
+
+    // Event payload. Multiple `data:` lines are joined with newlines.
+    pub data: String,
+}
+
+#[derive(Deserialize)]
+struct SseOpenOut (Result<u64, RequestError>);
+
+// The next raw chunk of the stream, or `None` when the server closed it.
+#[derive(Deserialize)]
+struct SseNextOut (Result<Option<String>, RequestError>);
+
+/// Splits the raw text of an event stream into events.
+#[derive(Default)]
+struct SseParser {
+    buffer: String,
+    last_id: Option<String>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+    }
+
+    /// Returns the next complete event in the buffer.
+    /// `eof` is set once the stream has ended, so a trailing \r can be treated as a line ending.
+    fn next_event(&mut self, eof: bool) -> Option<SseEvent> {
+        while let Some(line) = self.next_line(eof) {
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Processes a line of the stream, returning an event when a blank line completes one.
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                self.event = None;
+                return None;
+            }
+            return Some(SseEvent {
+                id: self.last_id.clone(),
+                event: self.event.take().unwrap_or_else(|| "message".to_string()),
+                data: std::mem::take(&mut self.data).join("\n"),
+            });
+        }
+        // Lines starting with a colon are comments, often used as keep-alives.
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            _ => {},
+        }
+        None
+    }
+
+    /// Takes the next complete line out of the buffer.
+    fn next_line(&mut self, eof: bool) -> Option<String> {
+        let idx = self.buffer.find(['\n', '\r'])?;
+        // A trailing \r might be the first half of a \r\n split across chunks.
+        if self.buffer.as_bytes()[idx] == b'\r' && idx + 1 == self.buffer.len() && !eof {
+            return None;
+        }
+        let line = self.buffer[..idx].to_string();
+        let skip = if self.buffer[idx..].starts_with("\r\n") { 2 } else { 1 };
+        self.buffer.drain(..idx + skip);
+        Some(line)
+    }
+}
+
+/// A stream of events from an open SSE connection.
+/// The host reads the response incrementally and hands over chunks of text as they arrive;
+/// the guest splits them into events.
+pub struct SseStream {
+    handle: u64,
+    parser: SseParser,
+    done: bool,
+}
+
+impl SseStream {
+    /// Closes the stream on the host.
+    pub fn close(self) {
+        drop(self)
+    }
+}
+
+impl Iterator for SseStream {
+    type Item = Result<SseEvent, RequestError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.parser.next_event(self.done) {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+            let offset = unsafe { host_sse_next(self.handle) };
+            let (offset, size) = vec_parts_from_host(offset);
+            let out: SseNextOut = value_from_host(offset, size);
+            match out.0 {
+                Ok(Some(chunk)) => self.parser.push(&chunk),
+                // The stream ended. An unterminated event at the end is dropped, as the spec requires.
+                Ok(None) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                },
+            }
+        }
+    }
+}
+
+impl Drop for SseStream {
+    fn drop(&mut self) {
+        unsafe { host_sse_close(self.handle) };
+    }
+}
+
+impl RequestBuilder {
+    /// Opens the request as a Server-Sent Events stream and returns an iterator of events.
+    /// Each call to `next` blocks until the host has received the next event.
+    pub fn call_sse(&self) -> Result<SseStream, RequestError> {
+        self.validate()?;
+        let input = self.prepare()?;
+        let (offset, size) = value_to_host(&input);
+        let offset = unsafe { host_sse_open(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: SseOpenOut = value_from_host(offset, size);
+        out.0.map(|handle| SseStream { handle, parser: SseParser::default(), done: false })
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_sse_open(offset: u32, size: u32) -> u32;
+    pub fn host_sse_next(handle: u64) -> u32;
+    pub fn host_sse_close(handle: u64);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sse::*;
+
+    #[test]
+    fn test_parse_events() {
+        let mut parser = SseParser::default();
+        parser.push(": keep-alive\r\nevent: delta\r");
+        assert_eq!(parser.next_event(false), None);
+        parser.push("\ndata: one\r\ndata:two\r\nid: 7\r\n\r\ndata: {}\n\n");
+        assert_eq!(parser.next_event(false), Some(SseEvent { id: Some("7".to_string()), event: "delta".to_string(), data: "one\ntwo".to_string() }));
+        assert_eq!(parser.next_event(false), Some(SseEvent { id: Some("7".to_string()), event: "message".to_string(), data: "{}".to_string() }));
+        assert_eq!(parser.next_event(true), None);
+    }
+}
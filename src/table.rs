@@ -0,0 +1,64 @@
+use anyhow::Error;
+use schemars::JsonSchema;
+use schemars::schema::{InstanceType, ObjectValidation, SchemaObject};
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+use crate::{Resumable, PromptBuilder, PromptOutcome, Widget};
+use crate::prompt::parse_value;
+
+// Name of the single field of a table form.
+const ROWS: &str = "rows";
+
+/// Asks the user to fill out a table, one row per `T`, with a column per field of `T`.
+/// Users can edit the given `rows`, and add or remove rows. Pass no rows to start with an empty table.
+/// Handy for line items or bulk edits.
+pub fn prompt_table<T, S: Into<String>>(label: S, rows: &[T]) -> Resumable<PromptOutcome<Vec<T>>> where T: JsonSchema + Serialize + for<'de> Deserialize<'de> {
+    let rows = match serde_json::to_value(rows) {
+        Ok(rows) => rows,
+        Err(err) => return Resumable::Ready(PromptOutcome::Error(Error::new(err).context("Serialize error"))),
+    };
+    table_builder::<T>(label.into())
+        .prefill(json!({ ROWS: rows }))
+        .prompt_until_valid(|mut value| parse_value(value[ROWS].take()))
+}
+
+/// Builds a form with a single field, rendered as a table, holding a list of `T`.
+fn table_builder<T: JsonSchema>(label: String) -> PromptBuilder {
+    let mut schema = schemars::schema_for!(Vec<T>);
+    let mut rows = std::mem::take(&mut schema.schema);
+    rows.metadata().title = Some(label);
+
+    let mut object = ObjectValidation::default();
+    object.properties.insert(ROWS.to_string(), rows.into());
+    object.required.insert(ROWS.to_string());
+    schema.schema = SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(object)),
+        ..Default::default()
+    };
+    PromptBuilder::with_schema(schema).widget(ROWS, Widget::Table)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table::*;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct LineItem {
+        sku: String,
+        quantity: u32,
+    }
+
+    #[test]
+    fn test_table_schema() {
+        let form = serde_json::to_value(table_builder::<LineItem>("Items".to_string())).unwrap();
+        let rows = &form["schema"]["properties"]["rows"];
+        assert_eq!(rows["type"], "array");
+        assert_eq!(rows["title"], "Items");
+        assert_eq!(rows["items"]["$ref"], "#/definitions/LineItem");
+        assert_eq!(form["schema"]["definitions"]["LineItem"]["properties"]["quantity"]["type"], "integer");
+        assert_eq!(form["fields"]["rows"]["widget"], "Table");
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Resumable, Timestamp};
+use crate::workflow::{branch, parallel_begin, select_results};
+
+/// A timer started with `start_timer`, which runs while the function does other things and can be cancelled,
+/// e.g. "wait 3 days unless the customer replies sooner": `start_timer(three_days).race(|| await_event("reply"))?`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct TimerHandle(u32);
+
+/// Starts a timer that fires after `duration`. Timers are told apart by the order they're started in,
+/// so a replay gets the same timer back rather than starting a new one.
+pub fn start_timer(duration: Duration) -> TimerHandle {
+    let millis = duration.as_millis().try_into().unwrap_or(u64::MAX);
+    TimerHandle(unsafe { host_timer_start(millis) })
+}
+
+/// Starts a timer that fires at `time`.
+pub fn start_timer_until<T: Into<Timestamp>>(time: T) -> TimerHandle {
+    TimerHandle(unsafe { host_timer_start_until(time.into().as_millis()) })
+}
+
+/// Cancels a timer, so anything waiting on it resumes with `false`. Cancelling a timer that already fired does nothing.
+pub fn cancel_timer(handle: TimerHandle) {
+    unsafe { host_timer_cancel(handle.0) };
+}
+
+impl TimerHandle {
+    /// Pauses until the timer fires, returning `true`, or is cancelled, returning `false`.
+    pub fn wait(&self) -> Resumable<bool> {
+        match unsafe { host_timer_wait(self.0) } {
+            0 => Resumable::Pause,
+            1 => Resumable::Ready(true),
+            _ => Resumable::Ready(false),
+        }
+    }
+    pub fn cancel(&self) {
+        cancel_timer(*self);
+    }
+    /// Runs a step that may pause against the timer. Returns the step's output if it's done first, cancelling the timer,
+    /// or `None` once the timer fires. A cancelled timer never wins.
+    pub fn race<T, F>(self, f: F) -> Resumable<Option<T>> where F: FnOnce() -> Resumable<T> {
+        let group = parallel_begin();
+        let step = branch(group, 0, || f().map(Some));
+        let timer = branch(group, 1, || match self.wait()? {
+            true => Resumable::Ready(None),
+            false => Resumable::Pause,
+        });
+        let (winner, out) = select_results(group, vec![step, timer])?;
+        if winner == 0 {
+            self.cancel();
+        }
+        Resumable::Ready(out)
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_timer_start(millis: u64) -> u32;
+    pub fn host_timer_start_until(millis: u64) -> u32;
+    pub fn host_timer_wait(id: u32) -> u32;
+    pub fn host_timer_cancel(id: u32);
+}
@@ -0,0 +1,277 @@
+use std::{convert::Infallible, fmt};
+
+/// A parsed and validated absolute URL.
+/// Path segments and query parameters added through its methods are percent-encoded.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Url {
+    scheme: String,
+    userinfo: Option<String>,
+    host: String,
+    port: Option<u16>,
+    // Always starts with a slash. Already percent-encoded.
+    path: String,
+    // Without the leading question mark. Already percent-encoded.
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+/// Why a string isn't a usable URL.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum UrlError {
+    MissingScheme,
+    UnsupportedScheme(String),
+    MissingHost,
+    InvalidPort(String),
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::MissingScheme => write!(f, "URL is missing a scheme such as https://"),
+            UrlError::UnsupportedScheme(scheme) => write!(f, "URL scheme '{scheme}' is not supported"),
+            UrlError::MissingHost => write!(f, "URL is missing a host"),
+            UrlError::InvalidPort(port) => write!(f, "URL port '{port}' is not a valid port number"),
+            UrlError::InvalidCharacter(c) => write!(f, "URL contains the invalid character {c:?}"),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+impl From<Infallible> for UrlError {
+    fn from(err: Infallible) -> Self {
+        match err {}
+    }
+}
+
+impl Url {
+    /// Parses an absolute http, https, ws or wss URL.
+    pub fn parse(input: &str) -> Result<Self, UrlError> {
+        let input = input.trim();
+        if let Some(c) = input.chars().find(|c| c.is_whitespace() || c.is_control()) {
+            return Err(UrlError::InvalidCharacter(c));
+        }
+
+        let (scheme, rest) = input.split_once("://").ok_or(UrlError::MissingScheme)?;
+        let scheme = scheme.to_ascii_lowercase();
+        if !matches!(scheme.as_str(), "http" | "https" | "ws" | "wss") {
+            return Err(UrlError::UnsupportedScheme(scheme));
+        }
+
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment.to_string())),
+            None => (rest, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query.to_string())),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+
+        let (userinfo, host_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_port)) => (Some(userinfo.to_string()), host_port),
+            None => (None, authority),
+        };
+        // Leave IPv6 literals such as [::1]:8080 intact when splitting off the port.
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = port.parse::<u16>().map_err(|_| UrlError::InvalidPort(port.to_string()))?;
+                (host, Some(port))
+            },
+            _ => (host_port, None),
+        };
+        if host.is_empty() {
+            return Err(UrlError::MissingHost);
+        }
+        if let Some(c) = host.chars().find(|c| matches!(c, '<' | '>' | '"' | '{' | '}' | '|' | '\\' | '^' | '`')) {
+            return Err(UrlError::InvalidCharacter(c));
+        }
+
+        Ok(Url {
+            scheme,
+            userinfo,
+            host: host.to_ascii_lowercase(),
+            port,
+            path: path.to_string(),
+            query,
+            fragment,
+        })
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+    /// The percent-encoded path, always starting with a slash.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// The percent-encoded query string, without the question mark.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+    pub fn is_secure(&self) -> bool {
+        matches!(self.scheme.as_str(), "https" | "wss")
+    }
+
+    /// Appends a path segment, percent-encoding it. Slashes in the segment are encoded too.
+    pub fn join<S: AsRef<str>>(mut self, segment: S) -> Self {
+        if !self.path.ends_with('/') {
+            self.path.push('/');
+        }
+        self.path.push_str(&encode_component(segment.as_ref()));
+        self
+    }
+
+    /// Appends several path segments.
+    pub fn join_all<I, S>(self, segments: I) -> Self where I: IntoIterator<Item = S>, S: AsRef<str> {
+        segments.into_iter().fold(self, |url, segment| url.join(segment))
+    }
+
+    /// Appends a query parameter, percent-encoding the key and value.
+    pub fn with_query<S: AsRef<str>, S1: AsRef<str>>(mut self, key: S, value: S1) -> Self {
+        let pair = format!("{}={}", encode_component(key.as_ref()), encode_component(value.as_ref()));
+        self.query = Some(match self.query.take() {
+            Some(query) if !query.is_empty() => format!("{query}&{pair}"),
+            _ => pair,
+        });
+        self
+    }
+
+    /// The decoded query parameters, in order.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let query = match &self.query {
+            Some(query) => query,
+            None => return vec![],
+        };
+        query.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => (decode_component(key), decode_component(value)),
+                None => (decode_component(pair), String::new()),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://", self.scheme)?;
+        if let Some(userinfo) = &self.userinfo {
+            write!(f, "{userinfo}@")?;
+        }
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{query}")?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for Url {
+    type Error = UrlError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Url::parse(value)
+    }
+}
+
+impl TryFrom<&String> for Url {
+    type Error = UrlError;
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        Url::parse(value)
+    }
+}
+
+impl TryFrom<String> for Url {
+    type Error = UrlError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Url::parse(&value)
+    }
+}
+
+/// Percent-encodes everything except unreserved characters (RFC 3986).
+pub fn encode_component(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// Decodes percent-encoding, also treating `+` as a space as form-encoded queries do.
+/// Invalid escapes are kept as-is.
+pub fn decode_component(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    },
+                    None => out.push(b'%'),
+                }
+            },
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::url::*;
+
+    #[test]
+    fn test_parse() {
+        let url = Url::parse("HTTPS://user@Example.com:8443/a/b?q=1#top").unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.host(), "example.com");
+        assert_eq!(url.port(), Some(8443));
+        assert_eq!(url.path(), "/a/b");
+        assert_eq!(url.query(), Some("q=1"));
+        assert_eq!(url.to_string(), "https://user@example.com:8443/a/b?q=1#top");
+        assert_eq!(Url::parse("http://example.com").unwrap().path(), "/");
+
+        assert_eq!(Url::parse("example.com/a"), Err(UrlError::MissingScheme));
+        assert_eq!(Url::parse("ftp://example.com"), Err(UrlError::UnsupportedScheme("ftp".to_string())));
+        assert_eq!(Url::parse("https:///a"), Err(UrlError::MissingHost));
+        assert_eq!(Url::parse("https://example.com:http"), Err(UrlError::InvalidPort("http".to_string())));
+        assert_eq!(Url::parse("https://exa mple.com"), Err(UrlError::InvalidCharacter(' ')));
+    }
+
+    #[test]
+    fn test_join_and_query() {
+        let url = Url::parse("https://api.example.com/v1").unwrap()
+            .join_all(["users", "a/b c"])
+            .with_query("filter", "name=Zoë & co");
+        assert_eq!(url.to_string(), "https://api.example.com/v1/users/a%2Fb%20c?filter=name%3DZo%C3%AB%20%26%20co");
+        assert_eq!(url.query_pairs(), vec![("filter".to_string(), "name=Zoë & co".to_string())]);
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use regex_lite::Regex;
+use schemars::schema::{RootSchema, Schema};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::schema::SHOW_IF_EXTENSION;
+
+/// Error messages keyed by field path. Errors about the form as a whole use the empty path.
+pub type FieldErrors = BTreeMap<String, Vec<String>>;
+
+/// A problem with one field of a submission, found by the function's own checks.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct FieldError {
+    // Slash-separated path of the field, or empty for the form as a whole.
+    field: String,
+    message: String,
+}
+
+impl FieldError {
+    pub fn new<S: Into<String>, S1: Into<String>>(field: S, message: S1) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+    /// An error about the form as a whole rather than a single field.
+    pub fn form<S: Into<String>>(message: S) -> Self {
+        Self::new("", message)
+    }
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Groups errors by the field they belong to.
+pub(crate) fn group_errors(errors: Vec<FieldError>) -> FieldErrors {
+    let mut out = FieldErrors::new();
+    for error in errors {
+        out.entry(error.field).or_default().push(error.message);
+    }
+    out
+}
+
+/// A constraint on a single prompt field.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Rule {
+    // Numbers must be at least this large.
+    Min(f64),
+    // Numbers must be at most this large.
+    Max(f64),
+    // Strings must have at least this many characters, and lists this many items.
+    MinLength(usize),
+    // Strings must have at most this many characters, and lists this many items.
+    MaxLength(usize),
+    // Strings must contain a match of this regular expression. Anchor it with ^ and $ to match the whole value.
+    // A pattern that doesn't compile fails the prompt before it's shown.
+    Pattern(String),
+    // The field must be filled in. An empty string counts as not filled in.
+    Required,
+    // The field must be filled in when another field has the given value. An empty string counts as not filled in.
+    RequiredIf { field: String, equals: Value },
+}
+
+impl Rule {
+    /// Checks a field's value, which is `None` when the field was left out.
+    /// `form` is the whole submitted value, needed for rules that look at other fields.
+    fn check(&self, value: Option<&Value>, form: &Value) -> Result<(), String> {
+        let value = match value {
+            Some(Value::Null) | None => None,
+            Some(Value::String(s)) if s.is_empty() => None,
+            Some(value) => Some(value),
+        };
+        match (self, value) {
+            (Rule::Required, None) => Err("This field is required".to_string()),
+            (Rule::RequiredIf { field, equals }, None) if lookup(form, field) == Some(equals) => {
+                Err("This field is required".to_string())
+            },
+            (Rule::Min(min), Some(Value::Number(n))) if n.as_f64().is_some_and(|n| n < *min) => {
+                Err(format!("Must be at least {min}"))
+            },
+            (Rule::Max(max), Some(Value::Number(n))) if n.as_f64().is_some_and(|n| n > *max) => {
+                Err(format!("Must be at most {max}"))
+            },
+            (Rule::MinLength(min), Some(Value::String(s))) if s.chars().count() < *min => {
+                Err(format!("Must be at least {min} characters long"))
+            },
+            (Rule::MaxLength(max), Some(Value::String(s))) if s.chars().count() > *max => {
+                Err(format!("Must be at most {max} characters long"))
+            },
+            (Rule::MinLength(min), Some(Value::Array(items))) if items.len() < *min => {
+                Err(format!("Must have at least {min} items"))
+            },
+            (Rule::MaxLength(max), Some(Value::Array(items))) if items.len() > *max => {
+                Err(format!("Must have at most {max} items"))
+            },
+            // Patterns that don't compile are caught when the rule is added, as the user couldn't fix them.
+            (Rule::Pattern(pattern), Some(Value::String(s))) => match Regex::new(pattern) {
+                Ok(re) if !re.is_match(s) => Err(format!("Must match the pattern {pattern}")),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A set of rules for the fields of a prompt, checked against what the user submitted.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct Validator {
+    // Rules keyed by field path, e.g. "age" or "address/city".
+    rules: BTreeMap<String, Vec<Rule>>,
+
+    // Set when a rule was given that can't be checked, such as a pattern that doesn't compile.
+    // Prompting with the validator fails with it, rather than sending back errors the user can't fix.
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the constraints of the top-level properties of a schema,
+    /// such as those added with `#[validate(range(min = 1))]` or `#[validate(length(max = 20))]`.
+    pub fn from_schema(schema: &RootSchema) -> Self {
+        let mut validator = Self::new();
+        let object = match &schema.schema.object {
+            Some(object) => object,
+            None => return validator,
+        };
+        for name in &object.required {
+            // Fields that are only shown conditionally are only required while they're shown.
+            let show_if = match object.properties.get(name) {
+                Some(Schema::Object(property)) => property.extensions.get(SHOW_IF_EXTENSION),
+                _ => None,
+            };
+            let condition = show_if.and_then(|show_if| Some((show_if.get("field")?.as_str()?, show_if.get("equals")?)));
+            match condition {
+                Some((field, equals)) => validator.add(name, Rule::RequiredIf { field: field.to_string(), equals: equals.clone() }),
+                None => validator.add(name, Rule::Required),
+            }
+        }
+        for (name, property) in &object.properties {
+            let property = match property {
+                Schema::Object(property) => property,
+                Schema::Bool(_) => continue,
+            };
+            if let Some(number) = &property.number {
+                if let Some(min) = number.minimum {
+                    validator.add(name, Rule::Min(min));
+                }
+                if let Some(max) = number.maximum {
+                    validator.add(name, Rule::Max(max));
+                }
+            }
+            if let Some(string) = &property.string {
+                if let Some(min) = string.min_length {
+                    validator.add(name, Rule::MinLength(min as usize));
+                }
+                if let Some(max) = string.max_length {
+                    validator.add(name, Rule::MaxLength(max as usize));
+                }
+                if let Some(pattern) = &string.pattern {
+                    validator.add(name, Rule::Pattern(pattern.clone()));
+                }
+            }
+            if let Some(array) = &property.array {
+                if let Some(min) = array.min_items {
+                    validator.add(name, Rule::MinLength(min as usize));
+                }
+                if let Some(max) = array.max_items {
+                    validator.add(name, Rule::MaxLength(max as usize));
+                }
+            }
+        }
+        validator
+    }
+
+    /// Adds a rule for a field.
+    pub fn rule<S: Into<String>>(mut self, field: S, rule: Rule) -> Self {
+        self.add(field, rule);
+        self
+    }
+
+    pub(crate) fn add<S: Into<String>>(&mut self, field: S, rule: Rule) {
+        let field = field.into();
+        if let Rule::Pattern(pattern) = &rule {
+            if let Err(err) = Regex::new(pattern) {
+                self.error.get_or_insert(format!("Invalid pattern {pattern} for field {field}: {err}"));
+                return;
+            }
+        }
+        self.rules.entry(field).or_default().push(rule);
+    }
+
+    /// Adds all the rules of another validator.
+    pub(crate) fn extend(&mut self, other: &Validator) {
+        for (field, rules) in &other.rules {
+            self.rules.entry(field.clone()).or_default().extend(rules.iter().cloned());
+        }
+        if let Some(err) = &other.error {
+            self.error.get_or_insert(err.clone());
+        }
+    }
+
+    /// Whether there are no rules to check.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Why the validator can't be used, e.g. because a pattern doesn't compile.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Checks a submitted form, returning the error messages of every field that broke a rule.
+    pub fn check(&self, form: &Value) -> FieldErrors {
+        let mut errors = FieldErrors::new();
+        for (field, rules) in &self.rules {
+            let value = lookup(form, field);
+            for rule in rules {
+                if let Err(message) = rule.check(value, form) {
+                    errors.entry(field.clone()).or_default().push(message);
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Looks up a field of a form by its slash-separated path.
+fn lookup<'a>(form: &'a Value, path: &str) -> Option<&'a Value> {
+    form.pointer(&format!("/{path}"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use crate::validate::*;
+
+    #[test]
+    fn test_check() {
+        let validator = Validator::new()
+            .rule("age", Rule::Min(18.0))
+            .rule("name", Rule::Required)
+            .rule("name", Rule::MaxLength(5))
+            .rule("zip", Rule::Pattern("^[0-9]{5}$".to_string()))
+            .rule("address/city", Rule::RequiredIf { field: "ship".to_string(), equals: json!(true) });
+
+        let errors = validator.check(&json!({ "age": 12, "name": "Bartholomew", "zip": "1234", "ship": true, "address": {} }));
+        assert_eq!(errors["age"], vec!["Must be at least 18"]);
+        assert_eq!(errors["name"], vec!["Must be at most 5 characters long"]);
+        assert_eq!(errors["zip"], vec!["Must match the pattern ^[0-9]{5}$"]);
+        assert_eq!(errors["address/city"], vec!["This field is required"]);
+
+        let errors = validator.check(&json!({ "age": 30, "name": "Bart", "zip": "12345", "ship": false }));
+        assert!(errors.is_empty());
+        assert_eq!(validator.check(&json!({ "name": "" }))["name"], vec!["This field is required"]);
+    }
+
+    #[test]
+    fn test_required_rejects_empty_string() {
+        let validator = Validator::new()
+            .rule("name", Rule::Required)
+            .rule("city", Rule::RequiredIf { field: "ship".to_string(), equals: json!(true) });
+        let errors = validator.check(&json!({ "name": "", "city": "", "ship": true }));
+        assert_eq!(errors["name"], vec!["This field is required"]);
+        assert_eq!(errors["city"], vec!["This field is required"]);
+        assert!(validator.check(&json!({ "name": " ", "ship": false })).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let validator = Validator::new().rule("zip", Rule::Pattern("[0-9".to_string()));
+        assert!(validator.error().is_some_and(|err| err.starts_with("Invalid pattern [0-9 for field zip")));
+        assert!(validator.check(&json!({ "zip": "1234" })).is_empty());
+        let mut combined = Validator::new();
+        combined.extend(&validator);
+        assert!(combined.error().is_some());
+        assert_eq!(Validator::new().rule("zip", Rule::Pattern("^[0-9]+$".to_string())).error(), None);
+    }
+
+    #[test]
+    fn test_from_schema() {
+        let schema = crate::SchemaBuilder::new()
+            .integer("qty").required().min(1.0)
+            .text("reason").required().show_if("qty", json!(0))
+            .build();
+        let validator = Validator::from_schema(&schema);
+        assert_eq!(validator.check(&json!({ "qty": 0 }))["qty"], vec!["Must be at least 1"]);
+        assert_eq!(validator.check(&json!({ "qty": 0 }))["reason"], vec!["This field is required"]);
+        assert!(validator.check(&json!({ "qty": 2 })).is_empty());
+    }
+
+    #[test]
+    fn test_group_errors() {
+        let errors = group_errors(vec![
+            FieldError::new("sku", "Unknown product"),
+            FieldError::form("The store is closed"),
+            FieldError::new("sku", "Out of stock"),
+        ]);
+        assert_eq!(errors["sku"], vec!["Unknown product", "Out of stock"]);
+        assert_eq!(errors[""], vec!["The store is closed"]);
+    }
+}
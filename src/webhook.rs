@@ -0,0 +1,118 @@
+//! Verifies the signatures of webhook payloads a function receives as input.
+
+use std::fmt;
+
+use crate::datetime::now;
+use crate::signing::{HmacAlgorithm, verify_hmac};
+
+/// How long Stripe signatures stay valid by default, in seconds.
+pub const STRIPE_DEFAULT_TOLERANCE: u64 = 300;
+
+/// Why a webhook payload was rejected.
+#[derive(PartialEq, Debug, Clone)]
+pub enum WebhookError {
+    // The signature header couldn't be parsed.
+    Malformed(String),
+
+    // The signature doesn't match the payload.
+    Mismatch,
+
+    // The signature is valid, but older (or further in the future) than the tolerance, in seconds.
+    OutsideTolerance(u64),
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::Malformed(err) => write!(f, "Malformed webhook signature: {err}"),
+            WebhookError::Mismatch => write!(f, "Webhook signature doesn't match the payload"),
+            WebhookError::OutsideTolerance(age) => write!(f, "Webhook signature timestamp is {age} seconds off"),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+/// Checks a hex-encoded HMAC of `payload`, in constant time.
+pub fn verify<B: AsRef<[u8]>, P: AsRef<[u8]>>(secret: B, algorithm: HmacAlgorithm, payload: P, signature_hex: &str) -> Result<(), WebhookError> {
+    let tag = hex::decode(signature_hex.trim()).map_err(|err| WebhookError::Malformed(err.to_string()))?;
+    match verify_hmac(algorithm, secret.as_ref(), payload.as_ref(), &tag) {
+        true => Ok(()),
+        false => Err(WebhookError::Mismatch),
+    }
+}
+
+/// Checks a GitHub `X-Hub-Signature-256` header such as `sha256=757107...`.
+/// The older `X-Hub-Signature` header with a `sha1=` prefix is accepted too.
+pub fn verify_github<B: AsRef<[u8]>, P: AsRef<[u8]>>(secret: B, payload: P, header: &str) -> Result<(), WebhookError> {
+    let (algorithm, signature) = match header.trim().split_once('=') {
+        Some(("sha256", signature)) => (HmacAlgorithm::Sha256, signature),
+        Some(("sha1", signature)) => (HmacAlgorithm::Sha1, signature),
+        _ => return Err(WebhookError::Malformed("expected a sha256= or sha1= prefix".to_string())),
+    };
+    verify(secret, algorithm, payload, signature)
+}
+
+/// Checks a `Stripe-Signature` header such as `t=1492774577,v1=5257a8...`.
+/// Signatures older or newer than `tolerance` seconds are rejected, to stop replayed events.
+pub fn verify_stripe<B: AsRef<[u8]>, P: AsRef<[u8]>>(secret: B, payload: P, header: &str, tolerance: u64) -> Result<(), WebhookError> {
+    verify_stripe_at(secret.as_ref(), payload.as_ref(), header, tolerance, now())
+}
+
+fn verify_stripe_at(secret: &[u8], payload: &[u8], header: &str, tolerance: u64, now: u64) -> Result<(), WebhookError> {
+    let mut timestamp = None;
+    let mut signatures = vec![];
+    for item in header.split(',') {
+        match item.trim().split_once('=') {
+            Some(("t", value)) => {
+                let value = value.parse::<u64>().map_err(|_| WebhookError::Malformed(format!("invalid timestamp '{value}'")))?;
+                timestamp = Some(value);
+            },
+            Some(("v1", value)) => signatures.push(value),
+            // Other schemes, such as the v0 test signatures, are ignored.
+            _ => {},
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| WebhookError::Malformed("missing timestamp".to_string()))?;
+    if signatures.is_empty() {
+        return Err(WebhookError::Malformed("missing v1 signature".to_string()));
+    }
+
+    // Stripe signs "{timestamp}.{payload}".
+    let mut signed = format!("{timestamp}.").into_bytes();
+    signed.extend_from_slice(payload);
+    // Any of the v1 signatures may match: Stripe sends one per active secret while a secret is being rolled.
+    let matched = signatures.iter().any(|signature| verify(secret, HmacAlgorithm::Sha256, &signed, signature).is_ok());
+    if !matched {
+        return Err(WebhookError::Mismatch);
+    }
+    let age = now.abs_diff(timestamp);
+    if age > tolerance {
+        return Err(WebhookError::OutsideTolerance(age));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::webhook::*;
+    use crate::signing::{hmac, encode, SignatureEncoding};
+
+    #[test]
+    fn test_verify_github() {
+        // Example from GitHub's documentation on validating webhook deliveries.
+        let header = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert_eq!(verify_github("It's a Secret to Everybody", "Hello, World!", header), Ok(()));
+        assert_eq!(verify_github("It's a Secret to Everybody", "Hello, World?", header), Err(WebhookError::Mismatch));
+        assert!(matches!(verify_github("It's a Secret to Everybody", "Hello, World!", "md5=abc"), Err(WebhookError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_verify_stripe() {
+        let signature = encode(SignatureEncoding::Hex, &hmac(HmacAlgorithm::Sha256, b"whsec_test", b"1000.{\"id\":1}"));
+        let header = format!("t=1000,v1=deadbeef,v1={signature},v0=ignored");
+        assert_eq!(verify_stripe_at(b"whsec_test", b"{\"id\":1}", &header, 300, 1200), Ok(()));
+        assert_eq!(verify_stripe_at(b"whsec_test", b"{\"id\":1}", &header, 300, 1400), Err(WebhookError::OutsideTolerance(400)));
+        assert_eq!(verify_stripe_at(b"whsec_test", b"{\"id\":2}", &header, 300, 1200), Err(WebhookError::Mismatch));
+    }
+}
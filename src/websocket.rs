@@ -0,0 +1,94 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{Resumable, value_to_host, vec_parts_from_host, value_from_host};
+
+/// A message sent or received over a WebSocket.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Message {
+    Text(String),
+    Binary(#[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+/// A WebSocket connection held open by the host.
+/// The guest only keeps a handle; the socket itself lives on the host and outlives pauses.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct WebSocket {
+    handle: u64,
+}
+
+#[derive(Serialize)]
+struct WsConnectIn {
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+#[derive(Deserialize)]
+struct WsConnectOut (Result<u64, String>);
+
+#[derive(Serialize)]
+struct WsSendIn<'a> {
+    handle: u64,
+    message: &'a Message,
+}
+
+#[derive(Deserialize)]
+struct WsSendOut (Result<(), String>);
+
+// `None` means the socket was closed by the other side.
+#[derive(Deserialize)]
+struct WsRecvOut (Resumable<Result<Option<Message>, String>>);
+
+impl WebSocket {
+    /// Opens a WebSocket connection to the given ws:// or wss:// URL.
+    pub fn connect<S: Into<String>>(url: S) -> Result<Self, String> {
+        Self::connect_with_headers(url, vec![])
+    }
+
+    /// Opens a WebSocket connection, sending extra headers (for example Authorization) with the handshake.
+    pub fn connect_with_headers<S: Into<String>>(url: S, headers: Vec<(String, String)>) -> Result<Self, String> {
+        let input = WsConnectIn { url: url.into(), headers };
+        let (offset, size) = value_to_host(&input);
+        let offset = unsafe { host_ws_connect(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: WsConnectOut = value_from_host(offset, size);
+        out.0.map(|handle| WebSocket { handle })
+    }
+
+    /// Sends a message.
+    pub fn send(&self, message: &Message) -> Result<(), String> {
+        let input = WsSendIn { handle: self.handle, message };
+        let (offset, size) = value_to_host(&input);
+        let offset = unsafe { host_ws_send(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: WsSendOut = value_from_host(offset, size);
+        out.0
+    }
+
+    /// Sends a text message.
+    pub fn send_text<S: Into<String>>(&self, text: S) -> Result<(), String> {
+        self.send(&Message::Text(text.into()))
+    }
+
+    /// Receives the next message.
+    /// If nothing has arrived yet, the multi-step function pauses until the host has a message for us.
+    /// Returns `None` once the connection is closed.
+    pub fn recv(&self) -> Resumable<Result<Option<Message>, String>> {
+        let offset = unsafe { host_ws_recv(self.handle) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: WsRecvOut = value_from_host(offset, size);
+        out.0
+    }
+
+    /// Closes the connection.
+    pub fn close(self) {
+        unsafe { host_ws_close(self.handle) };
+    }
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_ws_connect(offset: u32, size: u32) -> u32;
+    pub fn host_ws_send(offset: u32, size: u32) -> u32;
+    pub fn host_ws_recv(handle: u64) -> u32;
+    pub fn host_ws_close(handle: u64);
+}
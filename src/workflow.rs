@@ -0,0 +1,726 @@
+//! Building blocks for multistep functions, whose locals don't survive a pause.
+
+use std::{cell::Cell, fmt, marker::PhantomData, thread::LocalKey, time::Duration};
+
+use anyhow::Error;
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_bytes::ByteBuf;
+use serde_json::Value;
+
+use crate::{value_to_host, vec_parts_from_host, value_from_host, context, pause, Resumable, RetryLater, RetryPolicy, Timestamp};
+use crate::aws::sha256_hex;
+use crate::ids::format_uuid;
+use crate::datetime::now_millis;
+use crate::metrics;
+use crate::replay;
+
+/// A value kept by the host under `key` for the rest of the run, e.g. a loop counter or results gathered so far.
+/// A multistep function starts over on every resume, so locals are recomputed from nothing; state isn't.
+/// Code before a pause runs again on every replay, so change state inside a `step`, e.g.
+/// `step(format!("count/{i}"), || state::<u32, _>("count").update(|count| *count += 1))?`, to change it once per run.
+pub fn state<T, S: Into<String>>(key: S) -> State<T> where T: Serialize + DeserializeOwned {
+    State { key: key.into(), _value: PhantomData }
+}
+
+/// A handle on a value the host keeps for the rest of the run. See `state`.
+pub struct State<T> {
+    key: String,
+    _value: PhantomData<T>,
+}
+
+impl<T> State<T> where T: Serialize + DeserializeOwned {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+    /// The stored value, or `None` if nothing was stored yet.
+    /// A value stored as a different type, e.g. by an older version of the function, also reads as `None`.
+    pub fn get(&self) -> Option<T> {
+        let (offset, size) = value_to_host(&StateGetIn { key: &self.key });
+        let offset = unsafe { host_state_get(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: StateGetOut = value_from_host(offset, size);
+        rmp_serde::from_slice(&out.0?).ok()
+    }
+    pub fn get_or_default(&self) -> T where T: Default {
+        self.get().unwrap_or_default()
+    }
+    pub fn set(&self, value: &T) {
+        let value = rmp_serde::to_vec(value).expect("State::set: value doesn't serialize");
+        self.send(Some(value));
+    }
+    /// Changes the stored value, starting from its default if there's none, and returns the new value.
+    pub fn update<F: FnOnce(&mut T)>(&self, f: F) -> T where T: Default {
+        let mut value = self.get_or_default();
+        f(&mut value);
+        self.set(&value);
+        value
+    }
+    pub fn remove(&self) {
+        self.send(None);
+    }
+
+    fn send(&self, value: Option<Vec<u8>>) {
+        let (offset, size) = value_to_host(&StateSetIn { key: &self.key, value });
+        unsafe { host_state_set(offset, size) };
+    }
+}
+
+#[derive(Serialize)]
+struct StateGetIn<'a> {
+    key: &'a str,
+}
+
+// The stored value, encoded as MessagePack.
+#[derive(Deserialize)]
+struct StateGetOut (#[serde(with = "serde_bytes")] Option<Vec<u8>>);
+
+// Stores a value encoded as MessagePack, or removes it when there's none.
+#[derive(Serialize)]
+struct StateSetIn<'a> {
+    key: &'a str,
+    #[serde(with = "serde_bytes")]
+    value: Option<Vec<u8>>,
+}
+
+/// Runs `f` once per run, e.g. to send an email or charge a card, and returns its result.
+/// The host records the result under `name`; when the function is replayed after a pause, the recorded result
+/// is returned and `f` isn't run again. Names must be unique within the run, so include the index in a loop.
+/// How long `f` took is reported to the host's metrics.
+/// If the result holds a `RetryLater`, e.g. `Err(RetryLater(delay))`, it isn't recorded: the function pauses for the delay
+/// and `f` runs again on the replay after that.
+pub fn step<T, S, F>(name: S, f: F) -> Resumable<T> where T: Serialize + DeserializeOwned, S: Into<String>, F: FnOnce() -> T {
+    let name = name.into();
+    if let Some(result) = recorded_step(&name) {
+        return Resumable::Ready(result);
+    }
+    // Wait out the retries asked for on earlier replays, in order, to get to the attempt that hasn't run yet.
+    let mut retry = 1;
+    while let Some(millis) = recorded_step::<u64>(&format!("{name}/retry_later/{retry}")) {
+        pause(Duration::from_millis(millis))?;
+        retry += 1;
+    }
+
+    let started = now_millis();
+    let result = replay::without_recording(f);
+    metrics::record_step_duration(&name, Duration::from_millis(now_millis().saturating_sub(started) as u64));
+    RetryLater::take_serialized();
+    let encoded = rmp_serde::to_vec(&result).expect("step: result doesn't serialize");
+    if let Some(delay) = RetryLater::take_serialized() {
+        let millis: u64 = delay.as_millis().try_into().unwrap_or(u64::MAX);
+        record_step(&format!("{name}/retry_later/{retry}"), rmp_serde::to_vec(&millis).expect("step: delay doesn't serialize"))?;
+        pause(delay)?;
+        // The pause was only just asked for, so the host runs the function again once it's over.
+        return Resumable::Pause;
+    }
+    record_step(&name, encoded)?;
+    Resumable::Ready(result)
+}
+
+/// Records the encoded result of the step called `name`.
+fn record_step(name: &str, result: Vec<u8>) -> Resumable<()> {
+    let (offset, size) = value_to_host(&StepRecordIn { name, result });
+    // The host may pause to store the result durably before the function goes on.
+    let resume = unsafe { host_step_record(offset, size) };
+    match resume {
+        0 => Resumable::Pause,
+        _ => Resumable::Ready(()),
+    }
+}
+
+/// The recorded result of the step called `name`, if it already ran.
+pub(crate) fn recorded_step<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let (offset, size) = value_to_host(&StepIn { name });
+    let offset = unsafe { host_step_result(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: StepOut = value_from_host(offset, size);
+    // Running the step again could repeat its side effect, so a result that can't be read is a bug to fix.
+    Some(rmp_serde::from_slice(&out.0?).unwrap_or_else(|err| panic!("step {name}: recorded result doesn't deserialize: {err}")))
+}
+
+/// Runs a group of steps whose results are recorded all together or not at all, e.g. `transaction(|| { step_a()?; step_b() })`.
+/// The host holds on to the results of steps inside the group until it returns `Ok`, and then records them at once;
+/// if it fails, or the function dies midway, none are kept and the whole group runs again next time.
+/// Pausing inside the group also drops its results, so keep waits like prompts out of it.
+pub fn transaction<T, E, F>(f: F) -> Resumable<Result<T, E>> where F: FnOnce() -> Resumable<Result<T, E>> {
+    let id = unsafe { host_transaction_begin() };
+    let result = f();
+    let commit = matches!(result, Resumable::Ready(Ok(_)));
+    // The host may pause to store the results durably before the function goes on.
+    let resume = unsafe { host_transaction_end(id, commit as u32) };
+    match result {
+        Resumable::Ready(out) if resume != 0 => Resumable::Ready(out),
+        Resumable::Ready(_) | Resumable::Pause => Resumable::Pause,
+        Resumable::Cancelled => Resumable::Cancelled,
+    }
+}
+
+/// Reports how long a part of the function that may pause took to the host's metrics, counting the time it was paused,
+/// e.g. `timed_step("manager approval", || prompt_user("ann"))?` to see how long approvals wait.
+/// The clock starts the first time this is reached, so replays don't restart it. Names must be unique within the run.
+pub fn timed_step<T, S, F>(name: S, f: F) -> Resumable<T> where S: Into<String>, F: FnOnce() -> Resumable<T> {
+    let name = name.into();
+    // When the clock started, and whether the duration was reported, so replays past the end don't report it again.
+    let timing = state::<(u64, bool), _>(format!("timed_step/{name}"));
+    let (started_at, reported) = match timing.get() {
+        Some(timing) => timing,
+        None => {
+            let now = now_millis() as u64;
+            timing.set(&(now, false));
+            (now, false)
+        },
+    };
+    let out = f()?;
+    if !reported {
+        metrics::record_step_duration(&name, Duration::from_millis((now_millis() as u64).saturating_sub(started_at)));
+        timing.set(&(started_at, true));
+    }
+    Resumable::Ready(out)
+}
+
+/// Runs `f` until it succeeds or `policy` runs out of attempts, pausing with backoff between attempts.
+/// Each attempt is recorded like a `step` under `name` and its number, so a replay or a restart of the workflow
+/// picks up where the attempts left off instead of starting over. The error is that of the last attempt.
+/// An attempt that fails with `RetryLater` is followed by the delay it asks for; other failures wait for `policy.delay_for`,
+/// whose jitter comes from the host's randomness so runs that fail together don't retry in lockstep.
+pub fn with_retries<T, S, F>(name: S, policy: &RetryPolicy, mut f: F) -> Resumable<Result<T, Error>>
+where
+    T: Serialize + DeserializeOwned,
+    S: Into<String>,
+    F: FnMut() -> Result<T, Error>,
+{
+    let name = name.into();
+    let mut attempt = 1;
+    loop {
+        // The error is kept as its message and the delay of any `RetryLater`, as that's what can be recorded.
+        let result = step(format!("{name}/{attempt}"), || f().map_err(|err| {
+            let retry_later = RetryLater::find(&err).map(|delay| delay.as_millis().try_into().unwrap_or(u64::MAX));
+            (format!("{err:#}"), retry_later)
+        }))?;
+        match result {
+            Ok(value) => return Resumable::Ready(Ok(value)),
+            Err((message, _)) if attempt >= policy.max_attempts() => {
+                return Resumable::Ready(Err(Error::msg(format!("{name} failed after {attempt} attempts: {message}"))));
+            },
+            Err((_, Some(retry_later))) => pause(Duration::from_millis(retry_later))?,
+            Err((_, None)) => pause(policy.delay_for(attempt))?,
+        }
+        attempt += 1;
+    }
+}
+
+/// The error of a step that didn't complete before its deadline.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The step didn't complete before its deadline")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Runs a step that may pause, e.g. an `await_event` or a prompt, giving up on it once `timeout` has passed,
+/// or the overall deadline if that's sooner. The deadline is set the first time this is reached, so replays don't move it. Once a step times out,
+/// the host drops what it was waiting on, and replays return `TimedOut` without running it again.
+pub fn with_deadline<T, F>(timeout: Duration, f: F) -> Resumable<Result<T, TimedOut>> where F: FnOnce() -> Resumable<T> {
+    let timeout_millis = cap_timeout(timeout).as_millis().try_into().unwrap_or(u64::MAX);
+    let (offset, size) = value_to_host(&DeadlineIn { timeout_millis });
+    let offset = unsafe { host_deadline_begin(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let scope: DeadlineOut = value_from_host(offset, size);
+    if scope.timed_out {
+        return Resumable::Ready(Err(TimedOut));
+    }
+    let result = f();
+    // Ending the scope tells the host whether the step completed. If it's still waiting past the deadline, the step times out.
+    let completed = matches!(result, Resumable::Ready(_)) as u32;
+    let timed_out = unsafe { host_deadline_end(scope.id, completed) } != 0;
+    match result {
+        Resumable::Ready(out) => Resumable::Ready(Ok(out)),
+        Resumable::Pause if timed_out => Resumable::Ready(Err(TimedOut)),
+        Resumable::Pause => Resumable::Pause,
+        Resumable::Cancelled => Resumable::Cancelled,
+    }
+}
+
+#[derive(Serialize)]
+struct DeadlineIn {
+    timeout_millis: u64,
+}
+
+// The scope host calls are made in until it ends, and whether it already timed out.
+#[derive(Deserialize)]
+struct DeadlineOut {
+    id: u32,
+    timed_out: bool,
+}
+
+/// Calls `poll` every `interval` until it returns something, e.g. to wait for an export job to finish, giving up after `max_wait`.
+/// An error from `poll` ends the wait right away; so does the deadline passing, as a `TimedOut` converted into `E`.
+/// Each poll's result is recorded like a `step`, so replays get it back instead of calling the API again.
+/// Errors aren't recorded, as `E` needn't serialize; they end the wait anyway.
+pub fn poll_until<T, E, F>(interval: Duration, max_wait: Duration, mut poll: F) -> Resumable<Result<T, E>>
+where
+    T: Serialize + DeserializeOwned,
+    E: From<TimedOut>,
+    F: FnMut() -> Result<Option<T>, E>,
+{
+    let name = format!("poll_until/{}", next_call_id(&POLL_COUNT));
+    let result = with_deadline(max_wait, || {
+        let mut attempt = 1;
+        loop {
+            let key = format!("{name}/{attempt}");
+            let found = match recorded_step::<Option<T>>(&key) {
+                Some(found) => found,
+                None => match replay::without_recording(&mut poll) {
+                    Ok(found) => step(key, || found)?,
+                    Err(err) => return Resumable::Ready(Err(err)),
+                },
+            };
+            match found {
+                Some(out) => return Resumable::Ready(Ok(out)),
+                None => pause(interval)?,
+            }
+            attempt += 1;
+        }
+    })?;
+    Resumable::Ready(result.unwrap_or_else(|timed_out| Err(timed_out.into())))
+}
+
+/// A token for a call to an external API that must only take effect once, e.g. as the `Idempotency-Key` header of a payment.
+/// It's derived from the run ID and how many tokens the function took before, so replays get the same tokens in the same order.
+/// Take it right before the call, not inside a branch that only some replays reach.
+pub fn step_token() -> String {
+    let seq = next_call_id(&STEP_TOKEN_COUNT);
+    token_for(context().run_id(), seq)
+}
+
+fn token_for(run_id: &str, seq: u32) -> String {
+    let hash = sha256_hex(format!("{run_id}/{seq}").as_bytes());
+    let high = u64::from_str_radix(&hash[..16], 16).expect("sha256_hex returns hex");
+    let low = u64::from_str_radix(&hash[16..32], 16).expect("sha256_hex returns hex");
+    format_uuid(high, low)
+}
+
+thread_local! {
+    // The number of `poll_until` calls so far in this call of the multistep function, which names their steps.
+    static POLL_COUNT: Cell<u32> = const { Cell::new(0) };
+    // The number of `request_resumable` calls so far in this call of the multistep function, which names their attempts.
+    static REQUEST_COUNT: Cell<u32> = const { Cell::new(0) };
+    // The number of `pause_until_next` calls so far in this call of the multistep function, which names the times they wait for.
+    static SCHEDULE_COUNT: Cell<u32> = const { Cell::new(0) };
+    // The number of `step_token` calls so far in this call of the multistep function.
+    static STEP_TOKEN_COUNT: Cell<u32> = const { Cell::new(0) };
+    // The number of `with_overall_deadline` calls so far in this call of the multistep function, which names their deadlines.
+    static OVERALL_DEADLINE_COUNT: Cell<u32> = const { Cell::new(0) };
+    // The overall deadline in effect, in milliseconds since the unix epoch.
+    static OVERALL_DEADLINE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn next_call_id(counter: &'static LocalKey<Cell<u32>>) -> u32 {
+    counter.with(|count| {
+        count.set(count.get() + 1);
+        count.get()
+    })
+}
+
+/// The name the attempts of a `request_resumable` call are recorded under, by call order.
+pub(crate) fn next_request_name() -> String {
+    format!("request_resumable/{}", next_call_id(&REQUEST_COUNT))
+}
+
+/// The state key the wake time of a `pause_until_next` call is kept under, by call order.
+pub(crate) fn next_schedule_key() -> String {
+    format!("pause_until_next/{}", next_call_id(&SCHEDULE_COUNT))
+}
+
+/// Resets the counters that name things by call order, once the multistep function returns.
+pub(crate) fn reset_call_counters() {
+    POLL_COUNT.with(|count| count.set(0));
+    REQUEST_COUNT.with(|count| count.set(0));
+    SCHEDULE_COUNT.with(|count| count.set(0));
+    STEP_TOKEN_COUNT.with(|count| count.set(0));
+    OVERALL_DEADLINE_COUNT.with(|count| count.set(0));
+    OVERALL_DEADLINE.with(|overall| overall.set(None));
+}
+
+/// Runs part of a function, often all of it, under a deadline that the steps inside respect, giving up once `timeout` has passed.
+/// Requests, prompts, polls and `with_deadline` inside cut their own timeouts short to end by then,
+/// so a single prompt nobody answers can't hold the run up for longer. Nested overall deadlines can only make it sooner.
+pub fn with_overall_deadline<T, F>(timeout: Duration, f: F) -> Resumable<Result<T, TimedOut>> where F: FnOnce() -> Resumable<T> {
+    // The deadline is stored the first time this is reached, so replays don't move it.
+    let stored = state::<u64, _>(format!("overall_deadline/{}", next_call_id(&OVERALL_DEADLINE_COUNT)));
+    let deadline = match stored.get() {
+        Some(deadline) => deadline,
+        None => {
+            let deadline = (now_millis() as u64).saturating_add(timeout.as_millis().try_into().unwrap_or(u64::MAX));
+            stored.set(&deadline);
+            deadline
+        },
+    };
+    let outer = OVERALL_DEADLINE.with(Cell::get);
+    let deadline = outer.map_or(deadline, |outer| outer.min(deadline));
+    OVERALL_DEADLINE.with(|overall| overall.set(Some(deadline)));
+    let result = with_deadline(timeout, f);
+    OVERALL_DEADLINE.with(|overall| overall.set(outer));
+    result
+}
+
+/// The overall deadline set by the innermost `with_overall_deadline` running, if any.
+pub fn overall_deadline() -> Option<Timestamp> {
+    OVERALL_DEADLINE.with(Cell::get).map(Timestamp::from_millis)
+}
+
+/// Cuts a timeout short to end by the overall deadline, if there is one.
+pub(crate) fn cap_timeout(timeout: Duration) -> Duration {
+    match OVERALL_DEADLINE.with(Cell::get) {
+        Some(deadline) => timeout.min(Duration::from_millis(deadline.saturating_sub(now_millis() as u64))),
+        None => timeout,
+    }
+}
+
+/// Runs steps that may pause, e.g. prompts to different people, side by side, and resumes once all are done.
+/// Each step's host calls are kept apart, so one step pausing doesn't hold up or reorder the others.
+/// For steps of different types, use `join!`.
+pub fn join_all<T, F>(steps: Vec<F>) -> Resumable<Vec<T>> where F: FnOnce() -> Resumable<T> {
+    let group = parallel_begin();
+    let results = steps.into_iter().enumerate().map(|(index, f)| branch(group, index, f)).collect();
+    join_results(results)
+}
+
+/// Runs steps that may pause side by side, and resumes once the first of them is done, with its index and output.
+/// The host drops what the other steps were waiting on, and replays return the same winner.
+/// `select!` does the same for steps written out one by one.
+pub fn select_all<T, F>(steps: Vec<F>) -> Resumable<(usize, T)> where F: FnOnce() -> Resumable<T> {
+    let group = parallel_begin();
+    let results = steps.into_iter().enumerate().map(|(index, f)| branch(group, index, f)).collect();
+    select_results(group, results)
+}
+
+/// Starts a group of steps run side by side. Called by `join!` and `select!`.
+#[doc(hidden)]
+pub fn parallel_begin() -> u32 {
+    unsafe { host_parallel_begin() }
+}
+
+/// Runs one step of a group, keeping its host calls apart from those of the other steps. Called by `join!` and `select!`.
+#[doc(hidden)]
+pub fn branch<T, F: FnOnce() -> Resumable<T>>(group: u32, index: usize, f: F) -> Resumable<T> {
+    unsafe { host_branch_enter(group, index.try_into().unwrap_or(u32::MAX)) };
+    let result = f();
+    unsafe { host_branch_exit() };
+    result
+}
+
+/// Picks the first step of a group that's done, the way the host first saw it. Called by `select!`.
+#[doc(hidden)]
+pub fn select_results<T>(group: u32, mut results: Vec<Resumable<T>>) -> Resumable<(usize, T)> {
+    let first_ready = results.iter().position(|result| matches!(result, Resumable::Ready(_)));
+    let first_ready = first_ready.map_or(-1, |index| index as i32);
+    // The host keeps the first winner it's told about, so a replay can't pick a different one.
+    let winner = unsafe { host_parallel_select(group, first_ready) };
+    if results.iter().any(|result| matches!(result, Resumable::Cancelled)) {
+        return Resumable::Cancelled;
+    }
+    match usize::try_from(winner).ok().filter(|winner| *winner < results.len()) {
+        Some(winner) => match results.swap_remove(winner) {
+            Resumable::Ready(out) => Resumable::Ready((winner, out)),
+            _ => Resumable::Pause,
+        },
+        None => Resumable::Pause,
+    }
+}
+
+/// All the outputs, once every step is done.
+fn join_results<T>(results: Vec<Resumable<T>>) -> Resumable<Vec<T>> {
+    if results.iter().any(|result| matches!(result, Resumable::Cancelled)) {
+        return Resumable::Cancelled;
+    }
+    let mut outputs = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Resumable::Ready(out) => outputs.push(out),
+            _ => return Resumable::Pause,
+        }
+    }
+    Resumable::Ready(outputs)
+}
+
+/// Steps that each come with a way to undo them, e.g. booking a flight, a hotel and a car.
+/// When a step fails, the steps that completed before it are undone in reverse order.
+/// Steps and compensations are recorded like `step`, so each runs once per run however often the function is replayed.
+pub struct Saga {
+    name: String,
+
+    // How to undo each step that completed, in the order they completed.
+    compensations: Vec<(String, Compensation)>,
+}
+
+type Compensation = Box<dyn FnOnce() -> Result<(), Error>>;
+
+impl Saga {
+    /// `name` prefixes the names its steps are recorded under, so it must be unique within the run.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into(), compensations: vec![] }
+    }
+
+    /// Runs `action` once per run. If it succeeds, `compensate` is kept to undo it should a later step fail.
+    /// If it fails, the steps before it are undone and its error is returned.
+    pub fn step<T, S, F, C>(&mut self, name: S, action: F, compensate: C) -> Resumable<Result<T, Error>>
+    where
+        T: Serialize + DeserializeOwned + Clone + 'static,
+        S: Into<String>,
+        F: FnOnce() -> Result<T, Error>,
+        C: FnOnce(&T) -> Result<(), Error> + 'static,
+    {
+        let name = name.into();
+        // The error is kept as its message, as that's what can be recorded.
+        let result = step(format!("{}/{name}", self.name), || action().map_err(|err| format!("{err:#}")))?;
+        match result {
+            Ok(value) => {
+                let done = value.clone();
+                self.compensations.push((name, Box::new(move || compensate(&done))));
+                Resumable::Ready(Ok(value))
+            },
+            Err(message) => {
+                let message = format!("Step {name} of {} failed: {message}", self.name);
+                match self.compensate()? {
+                    Ok(()) => Resumable::Ready(Err(Error::msg(message))),
+                    Err(err) => Resumable::Ready(Err(Error::msg(format!("{message}. {err}")))),
+                }
+            },
+        }
+    }
+
+    /// Undoes the completed steps in reverse order, e.g. when something outside the saga fails.
+    /// Every compensation runs, even after one fails; the error lists those that failed.
+    pub fn compensate(&mut self) -> Resumable<Result<(), Error>> {
+        let mut failures = vec![];
+        while let Some((name, compensation)) = self.compensations.pop() {
+            let result = step(format!("{}/{name}/compensate", self.name), || compensation().map_err(|err| format!("{err:#}")))?;
+            if let Err(message) = result {
+                failures.push(format!("{name}: {message}"));
+            }
+        }
+        if failures.is_empty() {
+            Resumable::Ready(Ok(()))
+        } else {
+            Resumable::Ready(Err(Error::msg(format!("Undoing some steps failed: {}", failures.join("; ")))))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StepIn<'a> {
+    name: &'a str,
+}
+
+// The recorded result of a step that already ran, encoded as MessagePack.
+#[derive(Deserialize)]
+struct StepOut (#[serde(with = "serde_bytes")] Option<Vec<u8>>);
+
+#[derive(Serialize)]
+struct StepRecordIn<'a> {
+    name: &'a str,
+    #[serde(with = "serde_bytes")]
+    result: Vec<u8>,
+}
+
+/// Pauses until something emits an event called `name`, e.g. a webhook, a user action or another function,
+/// and returns its payload. Each call waits for an event it hasn't returned before; replays return the same events in order.
+/// Panics if the payload doesn't deserialize as a `T`.
+pub fn await_event<T, S: Into<String>>(name: S) -> Resumable<T> where T: DeserializeOwned {
+    let name = name.into();
+    let (offset, size) = value_to_host(&AwaitEventIn { name: &name });
+    let offset = unsafe { host_await_event(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: AwaitEventOut = value_from_host(offset, size);
+    let payload = out.0?;
+    let payload = rmp_serde::from_slice(&payload).unwrap_or_else(|err| panic!("await_event {name}: payload doesn't deserialize: {err}"));
+    Resumable::Ready(payload)
+}
+
+/// What `await_any` resumed with.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum EventOrTimeout {
+    // The first of the events to arrive. Use `serde_json::from_value` to read the payload as its type.
+    Event { name: String, payload: Value },
+    // None of the events arrived before the deadline.
+    Timeout,
+}
+
+/// Pauses until the first of several events arrives, e.g. `await_any(&["payment.succeeded", "payment.failed"], one_day)`,
+/// or `deadline` passes. Replays resume with the same event.
+pub fn await_any(names: &[&str], deadline: Duration) -> Resumable<EventOrTimeout> {
+    let first = with_deadline(deadline, || {
+        let waits = names.iter().map(|name| move || await_event::<Value, _>(*name)).collect();
+        select_all(waits)
+    })?;
+    match first {
+        Ok((index, payload)) => Resumable::Ready(EventOrTimeout::Event { name: names[index].to_string(), payload }),
+        Err(TimedOut) => Resumable::Ready(EventOrTimeout::Timeout),
+    }
+}
+
+/// Emits an event called `name`, resuming the functions waiting for it with `await_event`.
+pub fn emit_event<T, S: Into<String>>(name: S, payload: &T) where T: Serialize {
+    let payload = rmp_serde::to_vec(payload).expect("emit_event: payload doesn't serialize");
+    let (offset, size) = value_to_host(&EmitEventIn { name: &name.into(), payload });
+    unsafe { host_emit_event(offset, size) };
+}
+
+#[derive(Serialize)]
+struct AwaitEventIn<'a> {
+    name: &'a str,
+}
+
+// The event's payload, encoded as MessagePack.
+#[derive(Deserialize)]
+struct AwaitEventOut (Resumable<ByteBuf>);
+
+#[derive(Serialize)]
+struct EmitEventIn<'a> {
+    name: &'a str,
+    #[serde(with = "serde_bytes")]
+    payload: Vec<u8>,
+}
+
+/// Signals called `name` sent to this run since the last call, e.g. an operator's "skip this step", oldest first.
+/// Doesn't pause. Signals whose payload doesn't deserialize as a `T` are dropped.
+pub fn on_signal<T, S: Into<String>>(name: S) -> Vec<T> where T: DeserializeOwned {
+    let (offset, size) = value_to_host(&SignalIn { name: &name.into() });
+    let offset = unsafe { host_signals(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: SignalsOut = value_from_host(offset, size);
+    out.0.iter().filter_map(|payload| rmp_serde::from_slice(payload).ok()).collect()
+}
+
+/// Pauses until a signal called `name` is sent to this run, e.g. an operator's "cancel this order", and returns its payload.
+/// Signals whose payload doesn't deserialize as a `T` are dropped.
+pub fn await_signal<T, S: Into<String>>(name: S) -> Resumable<T> where T: DeserializeOwned {
+    let name = name.into();
+    loop {
+        let (offset, size) = value_to_host(&SignalIn { name: &name });
+        let offset = unsafe { host_await_signal(offset, size) };
+        let (offset, size) = vec_parts_from_host(offset);
+        let out: AwaitSignalOut = value_from_host(offset, size);
+        if let Ok(payload) = rmp_serde::from_slice(&out.0?) {
+            return Resumable::Ready(payload);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SignalIn<'a> {
+    name: &'a str,
+}
+
+// Payloads of the signals, encoded as MessagePack.
+#[derive(Deserialize)]
+struct SignalsOut (Vec<ByteBuf>);
+
+#[derive(Deserialize)]
+struct AwaitSignalOut (Resumable<ByteBuf>);
+
+/// Calls another Middle function by name and returns its output, so functions can be built from others.
+/// When the child is a multistep function and pauses, so does the caller; replays return the child's output without calling it again.
+pub fn call_function<In, Out, S: Into<String>>(name: S, input: &In) -> Resumable<Result<Out, Error>> where In: Serialize, Out: DeserializeOwned {
+    let name = name.into();
+    let input = match rmp_serde::to_vec(input) {
+        Ok(input) => input,
+        Err(err) => return Resumable::Ready(Err(Error::msg(format!("Input to {name} doesn't serialize: {err}")))),
+    };
+    let (offset, size) = value_to_host(&CallFunctionIn { name: &name, input });
+    let offset = unsafe { host_call_function(offset, size) };
+    let (offset, size) = vec_parts_from_host(offset);
+    let out: CallFunctionOut = value_from_host(offset, size);
+    let output = match out.0? {
+        Ok(output) => rmp_serde::from_slice(&output).map_err(|err| Error::msg(format!("Output of {name} doesn't deserialize: {err}"))),
+        Err(err_str) => Err(Error::msg(err_str)),
+    };
+    Resumable::Ready(output)
+}
+
+#[derive(Serialize)]
+struct CallFunctionIn<'a> {
+    name: &'a str,
+    #[serde(with = "serde_bytes")]
+    input: Vec<u8>,
+}
+
+// The child's output encoded as MessagePack, or why it failed.
+#[derive(Deserialize)]
+struct CallFunctionOut (Resumable<Result<ByteBuf, String>>);
+
+/// The version of the code the run was started under, as set with `#[workflow_version(n)]`.
+/// A run paused under old code resumes under new code, so a function whose steps changed can keep old runs on the old path,
+/// e.g. `if version_of_run() < 2 { old_step()? } else { new_step()? }`.
+/// Runs started before the function had a version are version 0.
+pub fn version_of_run() -> u32 {
+    unsafe { host_version_of_run() }
+}
+
+/// Tells the host which version of the function is running. The host keeps the version the run started under.
+/// Called by the code `#[workflow_version]` generates.
+#[doc(hidden)]
+pub fn set_workflow_version(version: u32) {
+    unsafe { host_set_workflow_version(version) };
+}
+
+#[link(wasm_import_module = "middle")]
+extern {
+    pub fn host_state_get(offset: u32, size: u32) -> u32;
+    pub fn host_state_set(offset: u32, size: u32);
+    pub fn host_step_result(offset: u32, size: u32) -> u32;
+    pub fn host_step_record(offset: u32, size: u32) -> u32;
+    pub fn host_await_event(offset: u32, size: u32) -> u32;
+    pub fn host_emit_event(offset: u32, size: u32);
+    pub fn host_signals(offset: u32, size: u32) -> u32;
+    pub fn host_await_signal(offset: u32, size: u32) -> u32;
+    pub fn host_call_function(offset: u32, size: u32) -> u32;
+    pub fn host_deadline_begin(offset: u32, size: u32) -> u32;
+    pub fn host_deadline_end(id: u32, completed: u32) -> u32;
+    pub fn host_parallel_begin() -> u32;
+    pub fn host_branch_enter(group: u32, index: u32);
+    pub fn host_branch_exit();
+    pub fn host_parallel_select(group: u32, first_ready: i32) -> i32;
+    pub fn host_set_workflow_version(version: u32);
+    pub fn host_version_of_run() -> u32;
+    pub fn host_transaction_begin() -> u32;
+    pub fn host_transaction_end(id: u32, commit: u32) -> u32;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::workflow::*;
+
+    #[test]
+    fn test_join_results() {
+        assert!(matches!(join_results(vec![Resumable::Ready(1), Resumable::Ready(2)]), Resumable::Ready(v) if v == [1, 2]));
+        assert!(matches!(join_results(vec![Resumable::Ready(1), Resumable::Pause]), Resumable::Pause));
+        assert!(matches!(join_results(vec![Resumable::Pause, Resumable::<u32>::Cancelled]), Resumable::Cancelled));
+    }
+
+    #[test]
+    fn test_cap_timeout() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(cap_timeout(hour), hour);
+        OVERALL_DEADLINE.with(|overall| overall.set(Some(now_millis() as u64 + 60_000)));
+        assert!(cap_timeout(hour) <= Duration::from_secs(60));
+        assert_eq!(cap_timeout(Duration::from_secs(1)), Duration::from_secs(1));
+        reset_call_counters();
+        assert_eq!(cap_timeout(hour), hour);
+    }
+
+    #[test]
+    fn test_token_for() {
+        let token = token_for("run-1", 1);
+        assert_eq!(token, token_for("run-1", 1));
+        assert_ne!(token, token_for("run-1", 2));
+        assert_ne!(token, token_for("run-2", 1));
+        assert_eq!(token.len(), 36);
+        assert_eq!(&token[14..15], "4");
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{HostRequestResponse, RequestBuilder, RequestError};
+use crate::headers;
+
+impl HostRequestResponse {
+    /// Deserializes an XML body, such as a SOAP envelope, an RSS feed or an S3 error.
+    pub fn xml_as<T: DeserializeOwned>(&self) -> Result<T, quick_xml::DeError> {
+        quick_xml::de::from_str(self.body())
+    }
+}
+
+impl RequestBuilder {
+    /// Sends `value` serialized as XML. Also sets the Content-Type header to application/xml, unless it's already set.
+    /// If the value can't be serialized, making the request fails with `RequestError::Serde`.
+    pub fn with_xml<T: Serialize>(self, value: &T) -> Self {
+        match quick_xml::se::to_string(value) {
+            Ok(xml) => {
+                let mut builder = self.with_body(xml);
+                builder.set_default_header(headers::CONTENT_TYPE, "application/xml");
+                builder
+            },
+            Err(err) => self.with_error(RequestError::Serde(err.to_string())),
+        }
+    }
+}